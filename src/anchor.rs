@@ -45,3 +45,81 @@ impl Anchor {
         (x, y)
     }
 }
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    /// Snap to the screen sides with anchors and margins
+    Anchor,
+    /// Open next to the mouse pointer
+    Pointer,
+    /// Open next to a rectangle, like a button on the bar
+    Relative(Rect),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl TryFrom<&String> for Rect {
+    type Error = CLIError;
+
+    fn try_from(s: &String) -> Result<Self, Self::Error> {
+        let mut values = s.split(',').map(|v| v.trim().parse::<i32>());
+
+        let mut next = || match values.next() {
+            Some(Ok(v)) => Ok(v),
+            _ => Err(CLIError::Rect(s.to_owned())),
+        };
+
+        let rect = Rect { x: next()?, y: next()?, width: next()?, height: next()? };
+
+        if next().is_ok() || rect.width < 0 || rect.height < 0 {
+            return Err(CLIError::Rect(s.to_owned()))
+        }
+
+        Ok(rect)
+    }
+}
+
+#[cfg(any(feature = "X11", feature = "Wayland"))]
+impl Rect {
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    /// Finds a spot for the window next to the target, that will fit into the screen.
+    /// Targets that are taller than they are wide (buttons on vertical bars) will be placed on
+    /// the side, everything else will be placed below or above.
+    pub fn popup(&self, window: (u32, u32), screen: Rect) -> (i32, i32) {
+        let (width, height) = (window.0 as i32, window.1 as i32);
+
+        let clamp = |v: i32, start: i32, length: i32, size: i32| {
+            v.min(start + length - size).max(start)
+        };
+
+        let (x, y) = if self.height > self.width {
+            let right = self.x + self.width;
+            let x = match right + width <= screen.x + screen.width {
+                true  => right,
+                false => self.x - width,
+            };
+
+            (x, self.y + self.height / 2 - height / 2)
+        }
+        else {
+            let bottom = self.y + self.height;
+            let y = match bottom + height <= screen.y + screen.height {
+                true  => bottom,
+                false => self.y - height,
+            };
+
+            (self.x + self.width / 2 - width / 2, y)
+        };
+
+        (clamp(x, screen.x, screen.width, width), clamp(y, screen.y, screen.height, height))
+    }
+}
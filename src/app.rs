@@ -1,23 +1,28 @@
 use std::borrow::Cow;
 use std::cell::Cell;
+use std::collections::BTreeMap;
 use std::rc::Rc;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use relm4::component::{AsyncComponent, AsyncComponentSender, AsyncComponentParts};
 use relm4::once_cell::sync::OnceCell;
 
 use gtk::glib::ControlFlow;
-use gtk::prelude::{ApplicationExt, GtkWindowExt, BoxExt, OrientableExt, WidgetExt, WidgetExtManual};
+use gtk::prelude::{ActionMapExt, ApplicationExt, GtkWindowExt, BoxExt, OrientableExt, WidgetExt, WidgetExtManual};
 use gtk::Orientation;
 
 use smallvec::SmallVec;
 use tokio_util::sync::CancellationToken;
 
-use crate::anchor::Anchor;
+use crate::anchor::{Anchor, Placement};
+use crate::desktop::{self, Resolver};
+use crate::dismiss::Dismiss;
 use crate::style::{self, StyleSettings};
-use crate::widgets::sliderbox::{SliderBox, SliderMessage, Sliders};
+use crate::widgets::sliderbox::{Group, GroupVolume, SliderBox, SliderMessage, Sliders, Sort};
+use crate::server::error::PulseError;
 use crate::server::{self, AudioServer, AudioServerEnum, Kind, MessageClient, MessageOutput, VolumeLevels};
+use crate::throttle::{Target, Throttle, Write};
 use crate::widgets::switchbox::{SwitchBox, Switches};
 
 pub static WM_CONFIG: OnceCell<WMConfig> = const { OnceCell::new() };
@@ -29,10 +34,34 @@ pub struct App {
     master: bool,
     sliders: Sliders,
     switches: Switches,
+    throttle: Throttle,
+    resolver: Resolver,
+    /// Mute state of every software client, whether it has a slider or not
+    clients: BTreeMap<u32, bool>,
+    /// Latest state of software clients that came before desktop entries were read
+    unresolved: BTreeMap<u32, Box<server::OutputClient>>,
+    solo: Option<Solo>,
     close_after: u32,
+    reordering: bool,
+    hide_on_close: bool,
+    transition: Duration,
 
     ready: Rc<Cell<bool>>,
     shutdown: Option<CancellationToken>,
+
+    /// Surface catching clicks outside of the window on Wayland
+    #[cfg(feature = "Wayland")]
+    catcher: Option<gtk::Window>,
+
+    /// Media players on the bus with their latest tracks
+    #[cfg(feature = "Mpris")]
+    players: Vec<(crate::mpris::Media, Option<crate::mpris::Track>)>,
+}
+
+/// Clients that are the only ones heard, and the ones that were muted to make it so.
+struct Solo {
+    ids: SmallVec<[u32; 3]>,
+    muted: Vec<u32>,
 }
 
 pub struct Config {
@@ -44,19 +73,37 @@ pub struct Config {
     pub horizontal: bool,
     pub master: bool,
     pub show_corked: bool,
-    pub per_process: bool,
+    pub group: Option<Group>,
+    pub group_volume: GroupVolume,
+    pub sort: Sort,
+    pub pins: Vec<String>,
+    pub priority: Vec<String>,
+    pub pause_meter: bool,
+    pub hide_on_close: bool,
+    pub transition: u32,
     pub userstyle: Option<std::path::PathBuf>,
 
     #[cfg(feature = "Accent")]
     pub accent: bool,
 
+    #[cfg(feature = "Mpris")]
+    pub media: bool,
+
     pub server: AudioServerEnum,
 }
 
 pub struct WMConfig {
     pub anchors: Anchor,
     pub margins: Vec<i32>,
+    pub placement: Placement,
     pub close_after: u32,
+    pub dismiss: Dismiss,
+
+    #[cfg(feature = "Wayland")]
+    pub layer_shell: crate::proto::wayland::LayerShellConfig,
+
+    #[cfg(feature = "X11")]
+    pub monitor: Option<crate::proto::x::MonitorSelector>,
 }
 
 #[derive(Debug)]
@@ -64,19 +111,44 @@ pub enum ElementMessage {
     SetMute { ids: SmallVec<[u32; 3]>, kind: server::Kind, flag: bool },
     SetVolume { ids: SmallVec<[u32; 3]>, kind: server::Kind, levels: VolumeLevels },
     SetOutput { name: Arc<str>, port: Arc<str> },
+    /// Mutes every other software client, or restores them if these clients are already soloed
+    Solo { ids: SmallVec<[u32; 3]> },
+    /// Solo of the application with this name
+    SoloApp { name: String },
+    /// Solo of the slider that has keyboard focus
+    SoloFocused,
+    EndSolo,
+    MuteAll,
+    UnmuteAll,
+    Move { ids: SmallVec<[u32; 3]>, output: String },
+    Hide { name: String },
+    Pin { name: String },
+    OpenMixer,
+    #[cfg(feature = "Mpris")]
+    Media { bus: Arc<str>, action: crate::mpris::Action },
     Remove { id: u32 },
+    Metering(bool),
+    Reorder,
     InterruptClose,
-    Close
+    Close,
+    Toggle,
 }
 
 #[derive(Debug, derive_more::From)]
 pub enum CommandMessage {
     #[from]
     Server(server::Message),
+    FlushVolume(Target),
+    #[cfg(feature = "Mpris")]
+    Media(crate::mpris::MediaMessage),
     SetStyle(Cow<'static, str>),
+    Desktop(desktop::Index),
     Success,
     #[allow(dead_code)] Connect,
     Show,
+    Reorder,
+    Close,
+    Closed,
     Quit,
 }
 
@@ -134,12 +206,12 @@ impl AsyncComponent for App {
 
         #[cfg(feature = "Wayland")]
         if crate::xdg::is_wayland() {
-            window.connect_realize(move |w| Self::init_wayland(w, config.anchors, &config.margins, config.close_after != 0));
+            window.connect_realize(move |w| Self::init_wayland(w, config));
         }
 
         #[cfg(feature = "X11")]
         if crate::xdg::is_x11() {
-            window.connect_realize(move |w| Self::realize_x11(w, config.anchors, config.margins.clone()));
+            window.connect_realize(move |w| Self::realize_x11(w, config));
         }
 
         None
@@ -181,6 +253,24 @@ impl AsyncComponent for App {
 
         App::connect(server.clone(), &sender);
 
+        // Desktop entries are read off the main thread, clients that come before them are renamed later
+        sender.oneshot_command(async {
+            let index = tokio::task::spawn_blocking(|| desktop::Index::build(&crate::xdg::data_dirs())).await.unwrap();
+            CommandMessage::Desktop(index)
+        });
+
+        #[cfg(feature = "Mpris")]
+        if config.media {
+            sender.command(|sender, shutdown| {
+                shutdown.register(async move {
+                    if let Err(e) = crate::mpris::watch(sender).await {
+                        eprintln!("{e}");
+                    }
+                })
+                .drop_on_shutdown()
+            });
+        }
+
         sender.oneshot_command(async move {
             use tokio::signal::*;
 
@@ -192,17 +282,41 @@ impl AsyncComponent for App {
 
         let mut sliders = Sliders::new(sender.input_sender());
         sliders.set_direction(wm_config.anchors, if config.horizontal { Orientation::Horizontal } else { Orientation::Vertical });
-        sliders.per_process = config.per_process;
+        sliders.group = config.group;
+        sliders.group_volume = config.group_volume;
+        sliders.sort = config.sort;
+        sliders.pins = config.pins;
+        sliders.priority = config.priority;
+
+        let aliases = crate::xdg::config_dir().join(crate::APP_BINARY).join("aliases");
+        let aliases = desktop::read_aliases(&aliases).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            Default::default()
+        });
 
-        let model = App {
+        let mut model = App {
             server,
             max_volume: config.max_volume,
             master: config.master,
             sliders,
             switches: Switches::new(sender.input_sender()),
+            throttle: Throttle::default(),
+            resolver: Resolver::new(aliases),
+            clients: BTreeMap::new(),
+            unresolved: BTreeMap::new(),
+            solo: None,
             ready: Rc::new(Cell::new(false)),
             shutdown: None,
+
+            #[cfg(feature = "Wayland")]
+            catcher: None,
+
+            #[cfg(feature = "Mpris")]
+            players: Vec::new(),
             close_after: wm_config.close_after,
+            reordering: false,
+            hide_on_close: config.hide_on_close,
+            transition: Duration::from_millis(config.transition as u64),
         };
 
         let switch_box = model.switches.container.widget();
@@ -217,7 +331,10 @@ impl AsyncComponent for App {
         window.set_default_height(config.height as i32);
         window.set_default_width(config.width as i32);
 
-        if wm_config.close_after != 0 {
+        let dismiss = wm_config.dismiss;
+
+        // Without explicit dismissal modes, window closes only after user has interacted with it
+        if dismiss.is_empty() && wm_config.close_after != 0 {
             let has_pointer = Rc::new(Cell::new(false));
 
             let controller = gtk::EventControllerMotion::new();
@@ -239,6 +356,133 @@ impl AsyncComponent for App {
             });
         }
 
+        if dismiss.contains(Dismiss::Escape) {
+            let controller = gtk::EventControllerKey::new();
+            controller.connect_key_pressed({
+                let sender = sender.clone();
+
+                move |_, key, _, _| match key {
+                    gtk::gdk::Key::Escape => {
+                        sender.command_sender().emit(CommandMessage::Close);
+                        gtk::glib::Propagation::Stop
+                    }
+                    _ => gtk::glib::Propagation::Proceed,
+                }
+            });
+            window.add_controller(controller);
+        }
+
+        if dismiss.contains(Dismiss::Focus) {
+            window.connect_is_active_notify({
+                let sender = sender.clone();
+
+                move |window| match window.is_active() {
+                    true  => sender.input(ElementMessage::InterruptClose),
+                    false => sender.input(ElementMessage::Close),
+                }
+            });
+        }
+
+        if dismiss.contains(Dismiss::Leave) {
+            let controller = gtk::EventControllerMotion::new();
+            controller.connect_enter({
+                let sender = sender.clone();
+                move |_, _, _| sender.input(ElementMessage::InterruptClose)
+            });
+            controller.connect_leave({
+                let sender = sender.clone();
+                move |_| sender.input(ElementMessage::Close)
+            });
+            window.add_controller(controller);
+        }
+
+        if dismiss.contains(Dismiss::Click) {
+            model.init_click_outside(&window, &sender);
+        }
+
+        if config.pause_meter {
+            window.connect_is_active_notify({
+                let sender = sender.clone();
+                move |window| sender.input(ElementMessage::Metering(window.is_active()))
+            });
+
+            window.connect_visible_notify({
+                let sender = sender.clone();
+                move |window| sender.input(ElementMessage::Metering(window.is_visible()))
+            });
+        }
+
+        // Class is removed on the next frame to let CSS transitions kick in
+        window.connect_show(|window| {
+            window.add_css_class("opening");
+            window.add_tick_callback(|window, _| {
+                window.remove_css_class("opening");
+                ControlFlow::Break
+            });
+        });
+
+        if config.hide_on_close {
+            window.connect_close_request({
+                let sender = sender.clone();
+
+                move |_| {
+                    sender.command_sender().emit(CommandMessage::Close);
+                    gtk::glib::Propagation::Stop
+                }
+            });
+        }
+
+        let toggle = gtk::gio::SimpleAction::new("toggle", None);
+        toggle.connect_activate({
+            let sender = sender.clone();
+            move |_, _| sender.input(ElementMessage::Toggle)
+        });
+        relm4::main_application().add_action(&toggle);
+
+        let mute_all = gtk::gio::SimpleAction::new("mute-all", None);
+        mute_all.connect_activate({
+            let sender = sender.clone();
+            move |_, _| sender.input(ElementMessage::MuteAll)
+        });
+        relm4::main_application().add_action(&mute_all);
+
+        let unmute_all = gtk::gio::SimpleAction::new("unmute-all", None);
+        unmute_all.connect_activate({
+            let sender = sender.clone();
+            move |_, _| sender.input(ElementMessage::UnmuteAll)
+        });
+        relm4::main_application().add_action(&unmute_all);
+
+        let solo = gtk::gio::SimpleAction::new("solo", Some(gtk::glib::VariantTy::STRING));
+        solo.connect_activate({
+            let sender = sender.clone();
+
+            move |_, name| if let Some(name) = name.and_then(|name| name.get::<String>()) {
+                sender.input(ElementMessage::SoloApp { name })
+            }
+        });
+        relm4::main_application().add_action(&solo);
+
+        let shortcuts = gtk::ShortcutController::new();
+
+        for (trigger, action) in [("<Control>m", "app.mute-all"), ("<Control><Shift>m", "app.unmute-all")] {
+            shortcuts.add_shortcut(gtk::Shortcut::new(gtk::ShortcutTrigger::parse_string(trigger), Some(gtk::NamedAction::new(action))));
+        }
+
+        shortcuts.add_shortcut(gtk::Shortcut::new(
+            gtk::ShortcutTrigger::parse_string("<Control>s"),
+            Some(gtk::CallbackAction::new({
+                let sender = sender.clone();
+
+                move |_, _| {
+                    sender.input(ElementMessage::SoloFocused);
+                    gtk::glib::Propagation::Stop
+                }
+            })),
+        ));
+
+        window.add_controller(shortcuts);
+
         window.add_tick_callback({
             let ready = model.ready.clone();
 
@@ -263,9 +507,54 @@ impl AsyncComponent for App {
         match message {
             CommandMessage::Server(msg) => self.handle_msg_cmd_server(msg, sender, window),
             CommandMessage::SetStyle(style) => relm4::set_global_css(&style),
-            CommandMessage::Show => window.set_visible(true),
+            CommandMessage::Desktop(index) => {
+                self.resolver.set_index(index);
+
+                for client in std::mem::take(&mut self.unresolved).into_values() {
+                    self.handle_msg_output_client(MessageClient::Changed(client), sender.clone(), window);
+                }
+            },
+            CommandMessage::Show => self.show(window),
+            CommandMessage::Reorder => {
+                self.reordering = false;
+                self.sliders.sort();
+            },
+            CommandMessage::FlushVolume(target) => {
+                if let Some(levels) = self.throttle.flush(target, Instant::now()) {
+                    self.set_volume(smallvec::smallvec![target.id], target.kind(), levels, &sender).await;
+                }
+            },
+            #[cfg(feature = "Mpris")]
+            CommandMessage::Media(msg) => self.handle_msg_media(msg),
             CommandMessage::Success => {},
             CommandMessage::Connect => App::connect(self.server.clone(), &sender),
+            CommandMessage::Close => {
+                if window.has_css_class("closing") { return }
+
+                if let Some(shutdown) = self.shutdown.take() {
+                    shutdown.cancel();
+                }
+
+                window.add_css_class("closing");
+
+                let duration = self.transition;
+
+                sender.oneshot_command(async move {
+                    tokio::time::sleep(duration).await;
+                    CommandMessage::Closed
+                });
+            },
+            CommandMessage::Closed => {
+                // Window was shown again while closing
+                if !window.has_css_class("closing") { return }
+
+                window.remove_css_class("closing");
+
+                match self.hide_on_close {
+                    true  => window.set_visible(false),
+                    false => sender.command_sender().emit(CommandMessage::Quit),
+                }
+            },
             CommandMessage::Quit => {
                 self.server.disconnect();
                 relm4::main_application().quit();
@@ -273,27 +562,122 @@ impl AsyncComponent for App {
         }
     }
 
-    async fn update(&mut self, message: Self::Input, sender: AsyncComponentSender<Self>, _: &Self::Root) {
+    async fn update(&mut self, message: Self::Input, sender: AsyncComponentSender<Self>, window: &Self::Root) {
         use ElementMessage::*;
 
         match message {
+            // Dragging produces far more values than the server needs, only the latest one is sent every interval
             SetVolume { ids, kind, levels } => {
-                self.server.set_volume(ids, kind, levels).await;
+                let now = Instant::now();
+                let mut ready = SmallVec::<[u32; 3]>::new();
+
+                for &id in &ids {
+                    let target = Target::new(id, kind);
+
+                    match self.throttle.write(target, levels.clone(), now) {
+                        Write::Now => ready.push(id),
+                        Write::Later(delay) => sender.oneshot_command(async move {
+                            tokio::time::sleep(delay).await;
+                            CommandMessage::FlushVolume(target)
+                        }),
+                        Write::Queued => {},
+                    }
+                }
+
+                if !ready.is_empty() {
+                    self.set_volume(ready, kind, levels, &sender).await;
+                }
             },
             Remove { id } => {
                 self.sliders.remove(id);
             }
-            SetMute { ids, kind, flag } => {
-                self.server.set_mute(ids, kind, flag).await;
-            }
+            SetMute { ids, kind, flag } => self.set_mute(ids, kind, flag, &sender).await,
             SetOutput { name, port } => {
-                self.server.set_output_by_name(&name, Some(&port)).await;
+                if let Err(e) = self.server.set_output_by_name(&name, Some(&port)).await {
+                    self.switches.fail(&name, &port);
+                    sender.command_sender().emit(CommandMessage::Server(server::Message::Error(e)));
+                }
+            }
+            Solo { ids } => self.solo(ids, &sender).await,
+            SoloApp { name } => {
+                let ids = self.sliders.ids_of(&name);
+
+                match ids.is_empty() {
+                    true  => crate::warnln!("There is no application named '{name}' to solo"),
+                    false => self.solo(ids, &sender).await,
+                }
+            }
+            SoloFocused => if let Some(ids) = self.sliders.focused() {
+                self.solo(ids, &sender).await
+            },
+            EndSolo => self.end_solo(&sender).await,
+            MuteAll | UnmuteAll => {
+                self.forget_solo();
+
+                let ids = self.clients.keys().copied().collect();
+                self.set_mute(ids, Kind::Software | Kind::Out, matches!(message, MuteAll), &sender).await;
+            }
+            Move { ids, output } => {
+                if let Err(e) = self.server.move_clients(ids.iter().copied(), &output).await {
+                    self.fail(&ids, Kind::Software | Kind::Out, e, &sender);
+                }
+            }
+            Hide { name } => self.sliders.hide(&name),
+            Pin { name } => self.sliders.toggle_pin(&name),
+            OpenMixer => sender.oneshot_command(async {
+                const PROGRAM: &str = "pavucontrol";
+
+                // Playback tab lists the clients
+                let child = tokio::process::Command::new(PROGRAM)
+                    .arg("--tab=1")
+                    .spawn();
+
+                if let Err(e) = child {
+                    eprintln!("{}", crate::error::Error::Launch { e, program: PROGRAM });
+                }
+
+                CommandMessage::Success
+            }),
+            #[cfg(feature = "Mpris")]
+            Media { bus, action } => sender.oneshot_command(async move {
+                if let Err(e) = crate::mpris::control(&bus, action).await {
+                    eprintln!("{e}");
+                }
+
+                CommandMessage::Success
+            }),
+            // Changes are batched, so sliders don't jump around with every update
+            Reorder => if !self.reordering {
+                self.reordering = true;
+
+                sender.oneshot_command(async {
+                    tokio::time::sleep(Duration::from_millis(500)).await;
+                    CommandMessage::Reorder
+                });
+            },
+            Metering(flag) => {
+                match self.server.set_peaking(flag, sender.command_sender()).await {
+                    Ok(_) | Err(server::error::Error::Pulse(PulseError::NotConnected)) => {},
+                    Err(e) => eprintln!("{e}"),
+                }
             }
             InterruptClose => {
                 if let Some(shutdown) = self.shutdown.take() {
                     shutdown.cancel();
                 }
             },
+            Toggle => {
+                if !self.ready.get() { return }
+
+                match window.is_visible() && !window.has_css_class("closing") {
+                    true  => sender.command_sender().emit(CommandMessage::Close),
+                    false => {
+                        window.remove_css_class("closing");
+                        self.show(window);
+                        window.present();
+                    }
+                }
+            },
             Close => {
                 if let Some(shutdown) = self.shutdown.take() {
                     shutdown.cancel();
@@ -308,7 +692,7 @@ impl AsyncComponent for App {
                     tokio::select! {
                         _ = token.cancelled() => CommandMessage::Success,
                         _ = tokio::time::sleep(duration) => {
-                            CommandMessage::Quit
+                            CommandMessage::Close
                         }
                     }
                 })
@@ -318,6 +702,37 @@ impl AsyncComponent for App {
 }
 
 impl App where App: AsyncComponent {
+    fn init_click_outside(&mut self, window: &<App as AsyncComponent>::Root, sender: &AsyncComponentSender<Self>) {
+        let quit = {
+            let sender = sender.command_sender().clone();
+            move || sender.emit(CommandMessage::Close)
+        };
+
+        #[cfg(feature = "Wayland")]
+        if crate::xdg::is_wayland() && gtk4_layer_shell::is_supported() {
+            self.catcher = Some(Self::init_catcher(window, WM_CONFIG.get().unwrap(), quit));
+            return
+        }
+
+        // Clicking outside of the window takes focus away from it
+        window.connect_is_active_notify(move |window| {
+            if !window.is_active() {
+                quit();
+            }
+        });
+    }
+
+    fn show(&self, window: &<App as AsyncComponent>::Root) {
+        #[cfg(feature = "Wayland")]
+        if let Some(catcher) = self.catcher.as_ref().filter(|catcher| !catcher.is_visible()) {
+            // Window is mapped without the catcher when it's first shown, so it's mapped again on top of it
+            window.set_visible(false);
+            Self::show_catcher(window, catcher);
+        }
+
+        window.set_visible(true);
+    }
+
     fn connect(server: Arc<AudioServerEnum>, sender: &AsyncComponentSender<Self>) {
         sender.spawn_command(move |sender| match server.connect(&sender) {
             Ok(_) | Err(server::error::Error::AlreadyConnected) => {},
@@ -325,6 +740,91 @@ impl App where App: AsyncComponent {
         })
     }
 
+    async fn set_volume(&mut self, ids: SmallVec<[u32; 3]>, kind: Kind, levels: VolumeLevels, sender: &AsyncComponentSender<Self>) {
+        if let Err(e) = self.server.set_volume(ids.iter().copied(), kind, levels).await {
+            for &id in &ids {
+                self.throttle.forget(Target::new(id, kind));
+            }
+
+            self.fail(&ids, kind, e, sender);
+        }
+    }
+
+    /// Mutes or unmutes clients, nothing is sent for an empty list.
+    async fn set_mute(&self, ids: SmallVec<[u32; 3]>, kind: Kind, flag: bool, sender: &AsyncComponentSender<Self>) {
+        if ids.is_empty() { return }
+
+        if let Err(e) = self.server.set_mute(ids.iter().copied(), kind, flag).await {
+            self.fail(&ids, kind, e, sender);
+        }
+    }
+
+    /// Mutes every other client, clients muted by an earlier solo stay muted.
+    async fn solo(&mut self, ids: SmallVec<[u32; 3]>, sender: &AsyncComponentSender<Self>) {
+        if self.solo.as_ref().is_some_and(|solo| solo.ids == ids) {
+            return self.end_solo(sender).await
+        }
+
+        let mut muted = self.forget_solo().map(|solo| solo.muted).unwrap_or_default();
+        muted.retain(|id| !ids.contains(id));
+
+        let others: SmallVec<[u32; 3]> = self.clients.iter()
+            .filter(|(id, muted)| !**muted && !ids.contains(id))
+            .map(|(&id, _)| id)
+            .collect();
+
+        muted.extend(&others);
+
+        let kind = Kind::Software | Kind::Out;
+
+        self.set_mute(others, kind, true, sender).await;
+        self.set_mute(ids.clone(), kind, false, sender).await;
+
+        for &id in &ids {
+            self.sliders.send(id, Kind::Software, SliderMessage::Solo(true));
+        }
+
+        self.solo = Some(Solo { ids, muted });
+    }
+
+    /// Unmutes clients that were muted by the solo and are still around.
+    async fn end_solo(&mut self, sender: &AsyncComponentSender<Self>) {
+        let Some(solo) = self.forget_solo() else { return };
+
+        let ids = solo.muted.into_iter()
+            .filter(|id| self.clients.contains_key(id))
+            .collect();
+
+        self.set_mute(ids, Kind::Software | Kind::Out, false, sender).await;
+    }
+
+    // Solo ends without restoring anything
+    fn forget_solo(&mut self) -> Option<Solo> {
+        let solo = self.solo.take()?;
+
+        for &id in &solo.ids {
+            self.sliders.send(id, Kind::Software, SliderMessage::Solo(false));
+        }
+
+        Some(solo)
+    }
+
+    // Sliders go back to the state confirmed by the server
+    fn fail(&self, ids: &[u32], kind: Kind, e: server::error::Error, sender: &AsyncComponentSender<Self>) {
+        // Rest of the batch went through when the server names the client it refused
+        let failed = e.client().filter(|id| ids.contains(id));
+        let ids = match &failed {
+            Some(id) => std::slice::from_ref(id),
+            None     => ids,
+        };
+
+        for &id in ids {
+            self.sliders.send(id, kind, SliderMessage::Failed);
+        }
+
+        sender.command_sender().emit(CommandMessage::Server(server::Message::Error(e)));
+    }
+
     fn handle_msg_cmd_server(&mut self, message: server::Message, sender: AsyncComponentSender<Self>, window: &<App as AsyncComponent>::Root) {
         use server::Message::*;
 
@@ -332,7 +832,7 @@ impl App where App: AsyncComponent {
             OutputClient(msg) => self.handle_msg_output_client(msg, sender, window),
             Output(msg) => self.handle_msg_output(msg),
             Ready => if !self.ready.replace(true) {
-                window.set_visible(true);
+                self.show(window);
 
                 let mut plan = Kind::Software
                         .union(Kind::Out);
@@ -343,10 +843,11 @@ impl App where App: AsyncComponent {
                     let master = self.master;
 
                     async move {
+                        // Outputs are also offered by the context menus of sliders
+                        server.request_outputs(&sender).await.unwrap();
+
                         if master {
                             plan |= Kind::Hardware;
-
-                            server.request_outputs(&sender).await.unwrap();
                             server.request_master(&sender).await.unwrap();
                         }
 
@@ -367,6 +868,10 @@ impl App where App: AsyncComponent {
 
                 self.sliders.clear();
                 self.switches.clear();
+                self.throttle = Throttle::default();
+                self.clients.clear();
+                self.unresolved.clear();
+                self.solo = None;
             }
             Disconnected(None) => sender.command_sender().emit(CommandMessage::Quit),
         }
@@ -376,26 +881,82 @@ impl App where App: AsyncComponent {
     fn handle_msg_output_client(&mut self, message: MessageClient, sender: AsyncComponentSender<Self>, window: &<Self as AsyncComponent>::Root) {
         match message {
             MessageClient::Peak(id, peak) => {
-                self.sliders.send(id, SliderMessage::ServerPeak(peak));
+                self.sliders.send(id, Kind::Software, SliderMessage::ServerPeak(peak));
             },
-            MessageClient::Changed(client) => {
-                self.sliders.send(client.id, SliderMessage::ServerChange(client));
+            MessageClient::Changed(mut client) => {
+                self.resolver.apply(&mut client);
+
+                // Server reports every intermediate value, slider should stay where the user left it
+                if let Some(muted) = self.clients.get_mut(&client.id).filter(|_| client.kind.contains(Kind::Software)) {
+                    *muted = client.muted;
+                }
+
+                if let Some(unresolved) = self.unresolved.get_mut(&client.id).filter(|_| client.kind.contains(Kind::Software)) {
+                    unresolved.clone_from(&client);
+                }
+
+                let shown = self.throttle.echo(Target::new(client.id, client.kind), &client.volume.levels, Instant::now());
+
+                self.sliders.send(client.id, client.kind, SliderMessage::ServerChange(client, shown));
             },
             MessageClient::New(client) => {
                 let mut client = *client;
                 client.max_volume = f64::min(client.max_volume, self.max_volume);
 
+                let (id, muted) = (client.id, client.muted);
+                let software = client.kind.contains(Kind::Software);
+
+                if software {
+                    self.clients.insert(id, muted);
+                }
+
+                if software && !self.resolver.is_indexed() {
+                    self.unresolved.insert(id, Box::new(client.clone()));
+                }
+
+                self.resolver.apply(&mut client);
+
                 self.sliders.push_client(client);
 
+                // Clients that arrive during a solo are muted as well, unless they join a soloed slider
+                if let Some(solo) = self.solo.as_mut().filter(|_| software) {
+                    if self.sliders.is_soloed(id) {
+                        solo.ids.push(id);
+                    }
+                    else if !muted {
+                        solo.muted.push(id);
+                        sender.input(ElementMessage::SetMute { ids: smallvec::smallvec![id], kind: Kind::Software | Kind::Out, flag: true });
+                    }
+                }
+
+                #[cfg(feature = "Mpris")]
+                for (media, track) in &self.players {
+                    self.sliders.attach(media, track.as_ref());
+                }
+
                 #[cfg(feature = "X11")]
                 if crate::xdg::is_x11() {
                     window.size_allocate(&window.allocation(), -1);
                 }
             },
             MessageClient::Removed(id) => {
-                if !self.sliders.contains(id) { return }
+                self.clients.remove(&id);
+                self.unresolved.remove(&id);
+
+                if !self.sliders.contains(id, Kind::Software) { return }
 
-                self.sliders.send(id, SliderMessage::Removed);
+                self.throttle.forget(Target::new(id, Kind::Software));
+
+                self.sliders.send(id, Kind::Software, SliderMessage::Removed);
+
+                // Solo ends with the last of its clients
+                if let Some(solo) = &mut self.solo {
+                    solo.ids.retain(|soloed| *soloed != id);
+
+                    if solo.ids.is_empty() {
+                        sender.input(ElementMessage::EndSolo);
+                    }
+                }
 
                 sender.command({
                     let sender = sender.input_sender().clone();
@@ -412,14 +973,42 @@ impl App where App: AsyncComponent {
         }
     }
 
+    #[cfg(feature = "Mpris")]
+    fn handle_msg_media(&mut self, msg: crate::mpris::MediaMessage) {
+        use crate::mpris::MediaMessage;
+
+        match msg {
+            MediaMessage::Appeared(media) => {
+                self.players.retain(|(m, _)| m.bus != media.bus);
+                self.sliders.attach(&media, None);
+                self.players.push((media, None));
+            },
+            MediaMessage::Track(bus, track) => {
+                if let Some((_, current)) = self.players.iter_mut().find(|(media, _)| media.bus == bus) {
+                    *current = Some(track.clone());
+                }
+
+                self.sliders.send_track(&bus, track);
+            },
+            MediaMessage::Vanished(bus) => {
+                self.players.retain(|(media, _)| media.bus != bus);
+                self.sliders.detach(&bus);
+            },
+        }
+    }
+
     fn handle_msg_output(&mut self, msg: MessageOutput) {
         match msg {
             MessageOutput::New(output) => {
-                self.switches.push(output);
+                if self.master {
+                    self.switches.push(output);
+                }
             },
             MessageOutput::Master(output) => {
                 self.switches.set_active(output);
-            }
+            },
+            MessageOutput::Sink(sink) => self.sliders.add_output(&sink),
+            MessageOutput::SinkRemoved(id) => self.sliders.remove_output(id),
         }
     }
 }
@@ -0,0 +1,183 @@
+use relm4::once_cell::sync::OnceCell;
+
+use crate::error::CLIError;
+use crate::server::Volume;
+
+pub static CONFIG: OnceCell<Config> = const { OnceCell::new() };
+
+/// Mapping between slider position and volume amplitude.
+#[derive(Clone, Copy, Default)]
+pub enum Curve {
+    /// Position is the amplitude itself
+    Linear,
+    /// Same as the percent shown by Pulse Audio and pavucontrol
+    #[default]
+    Cubic,
+    /// Position is proportional to the volume in dB, starting from the floor
+    Decibel { floor: f64 },
+}
+
+pub struct Config {
+    pub curve: Curve,
+    /// Show volume in dB instead of percents
+    pub decibels: bool,
+    /// Slider positions snap to multiples of it, 0 disables snapping
+    pub step: f64,
+    /// Distance around 100% that snaps to it, 0 disables the detent
+    pub detent: f64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            curve: Curve::default(),
+            decibels: false,
+            step: 0.01,
+            detent: 0.0,
+        }
+    }
+}
+
+impl Config {
+    /// Keyboard increment of the sliders.
+    pub fn increment(&self) -> f64 {
+        match self.step > 0.0 {
+            true  => self.step,
+            false => 0.005,
+        }
+    }
+
+    /// Moves a slider position to the nearest step or into the detent.
+    pub fn snap(&self, position: f64) -> f64 {
+        // Positions right at the edge of the detent are off by a rounding error
+        if (position - 1.0).abs() <= self.detent + 1e-9 {
+            return 1.0
+        }
+
+        match self.step > 0.0 {
+            true  => (position / self.step).round() * self.step,
+            false => position,
+        }
+    }
+}
+
+impl Curve {
+    pub fn parse(s: &str, floor: u8) -> Result<Self, CLIError> {
+        match s.as_bytes().first().map(u8::to_ascii_lowercase) {
+            Some(b'l') => Ok(Curve::Linear),
+            Some(b'c') => Ok(Curve::Cubic),
+            Some(b'd') => Ok(Curve::Decibel { floor: -(floor.max(1) as f64) }),
+            _          => Err(CLIError::Curve(s.to_owned())),
+        }
+    }
+
+    /// Converts amplitude into a slider position, 1.0 is the normal volume.
+    pub fn position(&self, amplitude: f64) -> f64 {
+        match *self {
+            _ if amplitude <= 0.0 => 0.0,
+            Curve::Linear => amplitude,
+            Curve::Cubic => amplitude.cbrt(),
+            Curve::Decibel { floor } => {
+                let db = 20.0 * amplitude.log10();
+                (1.0 - db / floor).max(0.0)
+            }
+        }
+    }
+
+    /// Converts a slider position back into amplitude.
+    pub fn amplitude(&self, position: f64) -> f64 {
+        match *self {
+            _ if position <= 0.0 => 0.0,
+            Curve::Linear => position,
+            Curve::Cubic => position.powi(3),
+            Curve::Decibel { floor } => {
+                let db = (1.0 - position) * floor;
+                10f64.powf(db / 20.0)
+            }
+        }
+    }
+}
+
+#[inline]
+pub fn config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// Text of the volume label.
+pub fn label(volume: &Volume) -> String {
+    match config().decibels {
+        true => match volume.amplitude() {
+            a if a <= 0.0 => "-∞dB".to_owned(),
+            a => format!("{}dB", (20.0 * a.log10()).round() + 0.0),
+        },
+        false => {
+            let mut s = ((volume.percent() * 100.0).round() as u32).to_string();
+            s.push('%');
+            s
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURVES: [Curve; 3] = [Curve::Linear, Curve::Cubic, Curve::Decibel { floor: -60.0 }];
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn round_trip() {
+        for curve in CURVES {
+            for i in 0..=300 {
+                let position = i as f64 / 100.0;
+                let back = curve.position(curve.amplitude(position));
+
+                assert!(close(back, position), "{position} came back as {back}");
+            }
+        }
+    }
+
+    #[test]
+    fn edges() {
+        for curve in CURVES {
+            assert_eq!(curve.amplitude(0.0), 0.0);
+            assert_eq!(curve.position(0.0), 0.0);
+            assert_eq!(curve.position(-1.0), 0.0);
+            assert_eq!(curve.amplitude(-1.0), 0.0);
+
+            // Normal volume is at the same place with every curve
+            assert!(close(curve.amplitude(1.0), 1.0));
+            assert!(close(curve.position(1.0), 1.0));
+        }
+
+        // Anything quieter than the floor is at the bottom
+        let decibel = Curve::Decibel { floor: -60.0 };
+        assert_eq!(decibel.position(10f64.powf(-80.0 / 20.0)), 0.0);
+        assert!(close(decibel.position(10f64.powf(-30.0 / 20.0)), 0.5));
+        assert!(close(Curve::Cubic.position(0.125), 0.5));
+    }
+
+    #[test]
+    fn snaps_to_steps() {
+        let config = Config { step: 0.05, detent: 0.03, ..Default::default() };
+
+        assert!(close(config.snap(0.0), 0.0));
+        assert!(close(config.snap(0.42), 0.4));
+        assert!(close(config.snap(0.96), 0.95));
+        assert!(close(config.increment(), 0.05));
+
+        // Detent wins over the nearest step
+        assert_eq!(config.snap(0.97), 1.0);
+        assert_eq!(config.snap(1.03), 1.0);
+        assert!(close(config.snap(1.04), 1.05));
+
+        let config = Config { step: 0.0, detent: 0.0, ..Default::default() };
+
+        assert_eq!(config.snap(0.123), 0.123);
+        assert_eq!(config.snap(1.0), 1.0);
+        assert!(config.increment() > 0.0);
+    }
+}
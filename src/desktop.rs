@@ -0,0 +1,390 @@
+//! Names and icons of applications from their desktop entries.
+//! https://specifications.freedesktop.org/desktop-entry-spec/latest/
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::error::ConfigError;
+use crate::server::{Kind, OutputClient};
+
+/// Part of a desktop entry that is shown on a slider.
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    /// Themed icon name, icons given by a path are ignored
+    pub icon: Option<String>,
+}
+
+#[derive(Default)]
+struct Fields {
+    name: Option<String>,
+    icon: Option<String>,
+    binary: Option<String>,
+    wm_class: Option<String>,
+    hidden: bool,
+}
+
+impl Fields {
+    fn parse(content: &str) -> Self {
+        let mut fields = Fields::default();
+        let mut main_group = false;
+
+        for line in content.lines().map(str::trim) {
+            if line.starts_with('[') {
+                main_group = line == "[Desktop Entry]";
+                continue
+            }
+
+            let Some((key, value)) = line.split_once('=').filter(|_| main_group) else { continue };
+            let value = value.trim();
+
+            // Localized keys like `Name[de]` are left out
+            match key.trim() {
+                "Name"           => fields.name = Some(value.to_owned()),
+                "Icon"           => fields.icon = Some(value.to_owned()),
+                "Exec"           => fields.binary = exec_binary(value),
+                "StartupWMClass" => fields.wm_class = Some(value.to_lowercase()),
+                "Hidden"         => fields.hidden = value == "true",
+                _                => {},
+            }
+        }
+
+        fields
+    }
+
+    fn entry(self) -> Option<Entry> {
+        if self.hidden {
+            return None
+        }
+
+        Some(Entry {
+            name: self.name.filter(|name| !name.is_empty())?,
+            icon: self.icon.filter(|icon| !icon.is_empty() && !icon.starts_with('/')),
+        })
+    }
+}
+
+/// Binary launched by the `Exec` key, skipping `env` and its variables.
+fn exec_binary(exec: &str) -> Option<String> {
+    let program = exec.split_whitespace()
+        .map(|token| token.trim_matches('"'))
+        .find(|token| *token != "env" && !token.contains('='))?;
+
+    let binary = Path::new(program).file_name()?.to_str()?.to_lowercase();
+
+    // Sandboxed applications are all launched by the same binary
+    (binary != "flatpak").then_some(binary)
+}
+
+/// Desktop entries of every data directory, reading them all takes a while.
+#[derive(Default, Debug)]
+pub struct Index {
+    /// Entries by their lowercase desktop file ID, without `.desktop`
+    entries: HashMap<String, Option<Arc<Entry>>>,
+    /// Binaries and window classes to desktop file IDs
+    hints: HashMap<String, String>,
+    /// Last parts of reverse DNS IDs to desktop file IDs, too generic to match anything but IDs
+    short: HashMap<String, String>,
+}
+
+impl Index {
+    pub fn build(dirs: &[PathBuf]) -> Self {
+        let mut index = Index::default();
+
+        for dir in dirs {
+            let root = dir.join("applications");
+            index.scan(&root, &root);
+        }
+
+        index
+    }
+
+    // Directories come in order of preference, so the first entry with an ID wins
+    fn scan(&mut self, root: &Path, dir: &Path) {
+        let Ok(files) = fs::read_dir(dir) else { return };
+
+        for file in files.flatten() {
+            let path = file.path();
+
+            if path.is_dir() {
+                self.scan(root, &path);
+                continue
+            }
+
+            if path.extension().is_none_or(|extension| extension != "desktop") {
+                continue
+            }
+
+            let Some(id) = path.strip_prefix(root).ok()
+                .and_then(|relative| relative.with_extension("").to_str().map(|id| id.replace('/', "-").to_lowercase()))
+            else { continue };
+
+            if self.entries.contains_key(&id) {
+                continue
+            }
+
+            let Ok(content) = fs::read_to_string(&path) else { continue };
+            let fields = Fields::parse(&content);
+
+            if !fields.hidden {
+                for hint in [fields.binary.clone(), fields.wm_class.clone()].into_iter().flatten() {
+                    self.hints.entry(hint).or_insert_with(|| id.clone());
+                }
+
+                if let Some((_, short)) = id.rsplit_once('.') {
+                    self.short.entry(short.to_owned()).or_insert_with(|| id.clone());
+                }
+            }
+
+            self.entries.insert(id, fields.entry().map(Arc::new));
+        }
+    }
+
+    fn get(&self, key: &str, short: bool) -> Option<Arc<Entry>> {
+        let key = key.to_lowercase();
+
+        if let Some(entry) = self.entries.get(&key) {
+            return entry.clone()
+        }
+
+        let id = self.hints.get(&key).or_else(|| self.short.get(&key).filter(|_| short))?;
+        self.entries.get(id).cloned().flatten()
+    }
+}
+
+/// Finds desktop entries of clients, nothing is found until the index is set.
+pub struct Resolver {
+    /// Lowercase client names, binaries or IDs to desktop file IDs
+    aliases: HashMap<String, String>,
+    index: Option<Index>,
+    clients: HashMap<String, Option<Arc<Entry>>>,
+}
+
+impl Resolver {
+    pub fn new(aliases: HashMap<String, String>) -> Self {
+        Self { aliases, index: None, clients: HashMap::new() }
+    }
+
+    pub fn is_indexed(&self) -> bool {
+        self.index.is_some()
+    }
+
+    pub fn set_index(&mut self, index: Index) {
+        self.index = Some(index);
+    }
+
+    /// Replaces name of a software client and fills in a missing icon.
+    pub fn apply(&mut self, client: &mut OutputClient) {
+        if client.kind.contains(Kind::Hardware) {
+            return
+        }
+
+        let Some(entry) = self.resolve(Identity::from(&*client)) else { return };
+
+        client.name.clone_from(&entry.name);
+
+        if client.icon.is_none() {
+            client.icon.clone_from(&entry.icon);
+        }
+    }
+
+    pub fn resolve(&mut self, client: Identity) -> Option<Arc<Entry>> {
+        // Process is only asked when the server didn't report a binary
+        let process = client.binary.is_none()
+            .then(|| client.process.and_then(process_binary))
+            .flatten();
+
+        // Clients with the same generic name can still come from different programs
+        let known = [client.app_id, client.entry, client.binary, Some(client.name)];
+        let key = known.into_iter().chain([process.as_deref()]).map(Option::unwrap_or_default).collect::<Vec<_>>().join("\n");
+
+        if let Some(entry) = self.clients.get(&key) {
+            return entry.clone()
+        }
+
+        let index = self.index.as_ref()?;
+        let aliased = known.iter().flatten().filter_map(|key| self.aliases.get(&key.to_lowercase()).map(String::as_str));
+
+        // Last parts of reverse DNS IDs are only looked up by IDs, names like `Client` are too common
+        let ids = [client.app_id, client.entry].into_iter().flatten().map(|key| (key, true));
+        let others = [client.binary, Some(client.name)].into_iter().flatten().chain(process.as_deref()).map(|key| (key, false));
+
+        let entry = aliased.map(|key| (key, false))
+            .chain(ids)
+            .chain(others)
+            .filter(|(key, _)| !key.is_empty())
+            .find_map(|(key, short)| index.get(key, short));
+
+        self.clients.insert(key, entry.clone());
+        entry
+    }
+}
+
+/// What the audio server knows about the application behind a client.
+#[derive(Clone, Copy, Default)]
+pub struct Identity<'a> {
+    pub name: &'a str,
+    pub binary: Option<&'a str>,
+    pub app_id: Option<&'a str>,
+    pub entry: Option<&'a str>,
+    pub process: Option<u32>,
+}
+
+impl <'a> From<&'a OutputClient> for Identity<'a> {
+    fn from(client: &'a OutputClient) -> Self {
+        Identity {
+            name: &client.name,
+            binary: client.binary.as_deref(),
+            app_id: client.app_id.as_deref(),
+            entry: client.entry.as_deref(),
+            process: client.process,
+        }
+    }
+}
+
+fn process_binary(pid: u32) -> Option<String> {
+    let exe = fs::read_link(format!("/proc/{pid}/exe")).ok()?;
+    exe.file_name()?.to_str().map(str::to_owned)
+}
+
+/// Reads `client = desktop entry` lines, empty lines and lines starting with `#` are skipped.
+pub fn read_aliases(path: &Path) -> Result<HashMap<String, String>, ConfigError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(ConfigError::Aliases { e, path: path.to_owned() }),
+    };
+
+    parse_aliases(&content).map_err(|line| ConfigError::Alias { line, path: path.to_owned() })
+}
+
+fn parse_aliases(content: &str) -> Result<HashMap<String, String>, usize> {
+    let mut aliases = HashMap::new();
+
+    for (i, line) in content.lines().map(str::trim).enumerate() {
+        if line.is_empty() || line.starts_with('#') {
+            continue
+        }
+
+        let (client, entry) = line.split_once('=')
+            .map(|(client, entry)| (client.trim(), entry.trim().trim_end_matches(".desktop")))
+            .filter(|(client, entry)| !client.is_empty() && !entry.is_empty())
+            .ok_or(i + 1)?;
+
+        aliases.insert(client.to_lowercase(), entry.to_owned());
+    }
+
+    Ok(aliases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIREFOX: &str = "\
+        [Desktop Entry]\n\
+        Name=Firefox\n\
+        Name[de]=Feuerfuchs\n\
+        Icon=firefox\n\
+        Exec=env MOZ_ENABLE_WAYLAND=1 /usr/lib/firefox/firefox %u\n\
+        \n\
+        [Desktop Action new-window]\n\
+        Name=New Window\n";
+
+    const SPOTIFY: &str = "\
+        [Desktop Entry]\n\
+        Name=Spotify\n\
+        Icon=com.spotify.Client\n\
+        Exec=/usr/bin/flatpak run --branch=stable com.spotify.Client\n";
+
+    const DISCORD: &str = "\
+        [Desktop Entry]\n\
+        Name=Discord\n\
+        Icon=/opt/discord/discord.png\n\
+        Exec=Discord\n\
+        StartupWMClass=discord\n";
+
+    fn resolver(aliases: &str) -> Resolver {
+        let dir = std::env::temp_dir().join(format!("mixxc-desktop-{}", std::process::id()));
+        let applications = dir.join("applications");
+        fs::create_dir_all(&applications).unwrap();
+
+        fs::write(applications.join("firefox.desktop"), FIREFOX).unwrap();
+        fs::write(applications.join("discord.desktop"), DISCORD).unwrap();
+        fs::write(applications.join("com.spotify.Client.desktop"), SPOTIFY).unwrap();
+
+        let mut resolver = Resolver::new(parse_aliases(aliases).unwrap());
+        resolver.set_index(Index::build(std::slice::from_ref(&dir)));
+
+        fs::remove_dir_all(dir).unwrap();
+        resolver
+    }
+
+    #[test]
+    fn parses_main_group() {
+        let fields = Fields::parse(FIREFOX);
+
+        assert_eq!(fields.binary.as_deref(), Some("firefox"));
+        assert_eq!(fields.entry(), Some(Entry { name: "Firefox".to_owned(), icon: Some("firefox".to_owned()) }));
+
+        assert_eq!(Fields::parse(SPOTIFY).binary, None);
+        assert_eq!(Fields::parse(DISCORD).entry().unwrap().icon, None);
+    }
+
+    #[test]
+    fn resolves_clients() {
+        let mut resolver = resolver("WEBRTC VoiceEngine = discord.desktop");
+
+        let firefox = resolver.resolve(Identity { name: "Firefox input", binary: Some("firefox"), ..Default::default() }).unwrap();
+        assert_eq!(*firefox, Entry { name: "Firefox".to_owned(), icon: Some("firefox".to_owned()) });
+
+        let spotify = resolver.resolve(Identity { name: "spotify", app_id: Some("com.spotify.Client"), ..Default::default() });
+        assert_eq!(spotify.unwrap().name, "Spotify");
+
+        // Generic last part of a reverse DNS ID only matches an ID
+        assert!(resolver.resolve(Identity { name: "Client", ..Default::default() }).is_none());
+
+        let spotify = resolver.resolve(Identity { name: "spotify", entry: Some("Client"), ..Default::default() });
+        assert_eq!(spotify.unwrap().name, "Spotify");
+
+        let discord = resolver.resolve(Identity { name: "WEBRTC VoiceEngine", ..Default::default() });
+        assert_eq!(discord.unwrap().name, "Discord");
+
+        // Window class of the same entry
+        let discord = resolver.resolve(Identity { name: "Chromium", binary: Some("Discord"), ..Default::default() });
+        assert_eq!(discord.unwrap().name, "Discord");
+
+        assert!(resolver.resolve(Identity { name: "Unknown", ..Default::default() }).is_none());
+    }
+
+    #[test]
+    fn resolves_processes() {
+        let binary = process_binary(std::process::id()).unwrap();
+
+        let dir = std::env::temp_dir().join(format!("mixxc-process-{}", std::process::id()));
+        let applications = dir.join("applications");
+        fs::create_dir_all(&applications).unwrap();
+
+        fs::write(applications.join("tests.desktop"), format!("[Desktop Entry]\nName=Tests\nExec={binary}\n")).unwrap();
+
+        let mut resolver = Resolver::new(HashMap::new());
+        resolver.set_index(Index::build(std::slice::from_ref(&dir)));
+
+        fs::remove_dir_all(dir).unwrap();
+
+        // Same generic name from another program doesn't share the result
+        assert!(resolver.resolve(Identity { name: "Playback", ..Default::default() }).is_none());
+
+        let tests = resolver.resolve(Identity { name: "Playback", process: Some(std::process::id()), ..Default::default() });
+        assert_eq!(tests.unwrap().name, "Tests");
+    }
+
+    #[test]
+    fn rejects_invalid_aliases() {
+        assert_eq!(parse_aliases("# comment\n\nfoo = bar\nbaz").unwrap_err(), 4);
+        assert_eq!(parse_aliases("foo =").unwrap_err(), 1);
+    }
+}
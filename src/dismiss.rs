@@ -0,0 +1,26 @@
+use crate::error::CLIError;
+
+bitflags::bitflags! {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct Dismiss: u8 {
+        const None    = 0b0000;
+        const Escape  = 0b0001;
+        const Focus   = 0b0010;
+        const Leave   = 0b0100;
+        const Click   = 0b1000;
+    }
+}
+
+impl TryFrom<&String> for Dismiss {
+    type Error = CLIError;
+
+    fn try_from(s: &String) -> Result<Self, Self::Error> {
+        match s.as_bytes().first().map(u8::to_ascii_lowercase) {
+            Some(b'e') => Ok(Dismiss::Escape),
+            Some(b'f') => Ok(Dismiss::Focus),
+            Some(b'l') => Ok(Dismiss::Leave),
+            Some(b'c') => Ok(Dismiss::Click),
+            _          => Err(CLIError::Dismiss(s.to_owned())),
+        }
+    }
+}
@@ -25,9 +25,20 @@ pub enum Error {
     #[error(transparent)]
     Cache(#[from] CacheError),
 
-    #[cfg(feature = "Accent")]
+    #[cfg(any(feature = "Accent", feature = "Mpris"))]
     #[error(transparent)]
-    Accent(#[from] ZbusError),
+    Zbus(#[from] ZbusError),
+
+    #[cfg(feature = "X11")]
+    #[error(transparent)]
+    X11(#[from] X11Error),
+
+    #[cfg(feature = "Mock")]
+    #[error(transparent)]
+    Mock(#[from] crate::server::error::MockError),
+
+    #[error("Unable to launch {program}\n{e}")]
+    Launch { e: io::Error, program: &'static str },
 }
 
 impl Debug for Error {
@@ -40,6 +51,35 @@ impl Debug for Error {
 pub enum CLIError {
     #[error("'{0}' is not a valid anchor point")]
     Anchor(String),
+
+    #[error("'{0}' is not a valid rectangle (expected x,y,width,height)")]
+    Rect(String),
+
+    #[error("'{0}' is not a valid dismissal mode")]
+    Dismiss(String),
+
+    #[error("'{0}' is not a valid grouping mode")]
+    Group(String),
+
+    #[error("'{0}' is not a valid group volume")]
+    GroupVolume(String),
+
+    #[error("'{0}' is not a valid sorting mode")]
+    Sort(String),
+
+    #[error("'{0}' is not a valid meter scale")]
+    MeterScale(String),
+
+    #[error("'{0}' is not a valid volume curve")]
+    Curve(String),
+
+    #[cfg(feature = "Wayland")]
+    #[error("'{0}' is not a valid layer")]
+    Layer(String),
+
+    #[cfg(feature = "Wayland")]
+    #[error("'{0}' is not a valid keyboard mode")]
+    KeyboardMode(String),
 }
 
 #[derive(Error, Debug)]
@@ -52,6 +92,12 @@ pub enum ConfigError {
 
     #[error("Unable to access a config directory\n{0} is not a directory")]
     NotDirectory(PathBuf),
+
+    #[error("Unable to read aliases ({path})\n{e}")]
+    Aliases { e: io::Error, path: PathBuf },
+
+    #[error("Invalid alias on line {line} ({path})\nExpected `client = desktop entry`")]
+    Alias { line: usize, path: PathBuf },
 }
 
 #[derive(Error, Debug)]
@@ -101,7 +147,7 @@ pub enum CacheError {
     MTime { e: io::Error, path: PathBuf },
 }
 
-#[cfg(feature = "Accent")]
+#[cfg(any(feature = "Accent", feature = "Mpris"))]
 #[derive(Error, Debug)]
 pub enum ZbusError {
     #[error("Couldn't establish a connection with the session bus\n{e}")]
@@ -114,5 +160,31 @@ pub enum ZbusError {
     Read { e: zbus::Error, namespace: String, key: String },
 
     #[error("Unable to parse unexpected result from the portal\n{v}")]
-    BadResult { v: String }
+    BadResult { v: String },
+
+    #[cfg(feature = "Mpris")]
+    #[error("Media player refused to `{method}`\n{e}")]
+    Call { e: zbus::Error, method: &'static str },
+}
+
+#[cfg(feature = "X11")]
+#[derive(Error, Debug)]
+pub enum X11Error {
+    #[error("Unable to connect to the X server\n{0}")]
+    Connect(x11rb::errors::ConnectError),
+
+    #[error("Unable to intern atoms\n{0}")]
+    Atoms(x11rb::errors::ReplyError),
+
+    #[error("Unable to set window manager properties\n{0}")]
+    Properties(x11rb::errors::ReplyError),
+
+    #[error("Unable to update _NET_WM_STATE\n{0}")]
+    State(x11rb::errors::ReplyError),
+
+    #[error("Unable to move the window\n{0}")]
+    Move(x11rb::errors::ReplyError),
+
+    #[error("Unable to query pointer position\n{0}")]
+    Pointer(x11rb::errors::ReplyError),
 }
@@ -1,7 +1,11 @@
 use std::path::PathBuf;
+use std::time::Duration;
 
 use error::{Error, ConfigError};
-use anchor::Anchor;
+use anchor::{Anchor, Placement, Rect};
+use dismiss::Dismiss;
+
+use gtk::glib::variant::ToVariant;
 
 static APP_NAME:   &str = "Mixxc";
 static APP_ID:     &str = "elvy.mixxc";
@@ -35,10 +39,23 @@ struct Args {
     #[argh(switch, short = 'C', long = "accent")]
     accent: bool,
 
+    #[cfg(feature = "Mpris")]
+    /// show media player controls next to their clients
+    #[argh(switch, long = "media")]
+    media: bool,
+
     /// margin distance for each anchor point
     #[argh(option, short = 'm', long = "margin")]
     margins: Vec<i32>,
 
+    /// open the window next to the mouse pointer
+    #[argh(switch, long = "at-pointer")]
+    at_pointer: bool,
+
+    /// open the window next to a rectangle: x,y,width,height
+    #[argh(option, long = "relative")]
+    relative: Option<String>,
+
     /// enable master volume slider
     #[argh(switch, short = 'M', long = "master")]
     master: bool,
@@ -55,18 +72,153 @@ struct Args {
     #[argh(option, short = 'c', long = "close")]
     close_after: Option<u32>,
 
+    /// close the window on: (e)scape, (f)ocus loss, pointer (l)eave, (c)lick outside
+    #[argh(option, short = 'd', long = "dismiss")]
+    dismiss: Vec<String>,
+
+    /// keep running in the background and hide the window instead of closing it
+    #[argh(switch, long = "hide")]
+    hide: bool,
+
+    /// show or hide the window of an already running instance
+    #[argh(switch, short = 't', long = "toggle")]
+    toggle: bool,
+
+    /// mute every application in an already running instance
+    #[argh(switch, long = "mute-all")]
+    mute_all: bool,
+
+    /// unmute every application in an already running instance
+    #[argh(switch, long = "unmute-all")]
+    unmute_all: bool,
+
+    /// mute every application but this one in an already running instance, again to restore them
+    #[argh(option, long = "solo")]
+    solo: Option<String>,
+
+    /// time (ms) to wait for the closing transition (default: 0)
+    #[argh(option, long = "transition")]
+    transition: Option<u32>,
+
     /// enable client icons
     #[argh(switch, short = 'i', long = "icon")]
     icon: bool,
 
-    /// max volume level in percent (default: 100; 1-255)
+    /// max volume level in percent, limited by the audio server (default: 100)
     #[argh(option, short = 'x', long = "max-volume")]
-    max_volume: Option<u8>,
+    max_volume: Option<u16>,
 
     /// use only one volume slider for each system process
     #[argh(switch, short = 'P', long = "per-process")]
     per_process: bool,
 
+    /// merge clients into expandable groups by: (p)rocess, (n)ame, (b)inary, (f)latpak
+    #[argh(option, short = 'g', long = "group")]
+    group: Option<String>,
+
+    /// volume of a group: max, mean (default: max)
+    #[argh(option, long = "group-volume")]
+    group_volume: Option<String>,
+
+    /// client order: arrival, name, activity, volume, priority (default: arrival)
+    #[argh(option, long = "sort")]
+    sort: Option<String>,
+
+    /// application name that always comes first, can be repeated
+    #[argh(option, long = "pin")]
+    pins: Vec<String>,
+
+    /// application name in order of priority for the priority sort, can be repeated
+    #[argh(option, long = "priority")]
+    priority: Vec<String>,
+
+    /// volume slider curve: (l)inear, (c)ubic, (d)ecibel (default: cubic)
+    #[argh(option, long = "curve")]
+    curve: Option<String>,
+
+    /// lowest volume of the decibel curve in -dB (default: 60)
+    #[argh(option, long = "curve-floor")]
+    curve_floor: Option<u8>,
+
+    /// show volume in dB instead of percents
+    #[argh(switch, long = "decibels")]
+    decibels: bool,
+
+    /// volume step in percent that sliders snap to, 0 to disable (default: 1)
+    #[argh(option, long = "step")]
+    step: Option<u8>,
+
+    /// distance in percent around 100% that sticks to it (default: 0)
+    #[argh(option, long = "detent")]
+    detent: Option<u8>,
+
+    /// peak meter scale: (l)inear, (d)ecibel (default: linear)
+    #[argh(option, long = "meter")]
+    meter: Option<String>,
+
+    /// dynamic range of the decibel peak meter in dB (default: 60)
+    #[argh(option, long = "meter-range")]
+    meter_range: Option<u8>,
+
+    /// time (ms) it takes for the peak meter to rise (default: 0)
+    #[argh(option, long = "meter-attack")]
+    meter_attack: Option<u32>,
+
+    /// time (ms) it takes for the peak meter to fall from full scale (default: 1000)
+    #[argh(option, long = "meter-release")]
+    meter_release: Option<u32>,
+
+    /// time (ms) to hold the highest peak, 0 to disable (default: 1000)
+    #[argh(option, long = "meter-hold")]
+    meter_hold: Option<u32>,
+
+    /// show a separate peak meter for each audio channel
+    #[argh(switch, long = "meter-channels")]
+    meter_channels: bool,
+
+    /// peak meter updates per second, 0 to disable (default: 30)
+    #[argh(option, long = "meter-rate")]
+    meter_rate: Option<u32>,
+
+    /// pause peak meter for paused clients and while window is unfocused or hidden
+    #[argh(switch, long = "meter-pause")]
+    meter_pause: bool,
+
+    #[cfg(feature = "Wayland")]
+    /// layer shell layer: (o)verlay, (t)op, (b)ottom (default: top)
+    #[argh(option, long = "layer")]
+    layer: Option<String>,
+
+    #[cfg(feature = "Wayland")]
+    /// output (monitor connector name) to open the window on, e.g. DP-1
+    #[argh(option, long = "output")]
+    output: Option<String>,
+
+    #[cfg(feature = "Wayland")]
+    /// layer shell namespace (default: volume-mixer)
+    #[argh(option, long = "namespace")]
+    namespace: Option<String>,
+
+    #[cfg(feature = "Wayland")]
+    /// size of the exclusive zone, -1 to ignore other exclusive zones
+    #[argh(option, long = "exclusive-zone")]
+    exclusive_zone: Option<i32>,
+
+    #[cfg(feature = "Wayland")]
+    /// keyboard interactivity: (n)one, (e)xclusive, (o)n-demand
+    #[argh(option, long = "keyboard")]
+    keyboard: Option<String>,
+
+    #[cfg(feature = "X11")]
+    /// monitor to open the window on: name, index, pointer or focused
+    #[argh(option, long = "monitor")]
+    monitor: Option<String>,
+
+    #[cfg(feature = "Mock")]
+    /// play a scripted timeline instead of connecting to the audio server
+    #[argh(option, long = "mock")]
+    mock: Option<PathBuf>,
+
     /// print version
     #[argh(switch, short = 'v')]
     version: bool,
@@ -92,19 +244,133 @@ fn main() -> Result<(), Error> {
         anchors |= a?;
     }
 
+    let mut dismiss = Dismiss::None;
+
+    for d in args.dismiss.iter().map(Dismiss::try_from) {
+        dismiss |= d?;
+    }
+
+    let placement = match (&args.relative, args.at_pointer) {
+        (Some(s), _)  => Placement::Relative(Rect::try_from(s)?),
+        (None, true)  => Placement::Pointer,
+        (None, false) => Placement::Anchor,
+    };
+
+    #[cfg(feature = "Wayland")]
+    let layer_shell = {
+        use proto::wayland::{self, LayerShellConfig};
+
+        let default = LayerShellConfig::default();
+
+        LayerShellConfig {
+            layer: args.layer.as_deref().map(wayland::parse_layer).transpose()?.unwrap_or(default.layer),
+            namespace: args.namespace.clone().unwrap_or(default.namespace),
+            output: args.output.clone(),
+            exclusive_zone: args.exclusive_zone,
+            keyboard: args.keyboard.as_deref().map(wayland::parse_keyboard_mode).transpose()?,
+        }
+    };
+
+    let group = match &args.group {
+        Some(s) => Some(widgets::sliderbox::Group::try_from(s)?),
+        None    => args.per_process.then_some(widgets::sliderbox::Group::Process),
+    };
+
+    let group_volume = args.group_volume.as_ref().map(widgets::sliderbox::GroupVolume::try_from).transpose()?.unwrap_or_default();
+
+    let sort = args.sort.as_ref().map(widgets::sliderbox::Sort::try_from).transpose()?.unwrap_or_default();
+
+    let curve = match &args.curve {
+        Some(s) => curve::Curve::parse(s, args.curve_floor.unwrap_or(60))?,
+        None    => curve::Curve::default(),
+    };
+
+    let meter_scale = match &args.meter {
+        Some(s) => meter::Scale::parse(s, args.meter_range.unwrap_or(60))?,
+        None    => meter::Scale::Linear,
+    };
+
     warning(&args);
 
     let app = relm4::RelmApp::new(crate::APP_ID).with_args(vec![]);
 
+    if args.toggle && activate_remote("toggle", None) {
+        return Ok(())
+    }
+
+    let remote = match (&args.solo, args.mute_all, args.unmute_all) {
+        (Some(name), _, _) => Some(("solo", Some(name.to_variant()))),
+        (None, true, _)    => Some(("mute-all", None)),
+        (None, _, true)    => Some(("unmute-all", None)),
+        _                  => None,
+    };
+
+    // Actions on clients only make sense for a mixer that already has them
+    if let Some((action, parameter)) = remote {
+        if !activate_remote(action, parameter.as_ref()) {
+            warnln!("There is no running instance of {APP_NAME} to {action}");
+        }
+
+        return Ok(())
+    }
+
     // Vertically oriented bars imply that we are stacking clients horizontally
     let horizontal = args.bar.unwrap_or_default().starts_with('v');
 
     app::WM_CONFIG.get_or_init(|| app::WMConfig {
         anchors,
         close_after: args.close_after.unwrap_or(0),
+        dismiss,
         margins: args.margins,
+        placement,
+
+        #[cfg(feature = "Wayland")]
+        layer_shell,
+
+        #[cfg(feature = "X11")]
+        monitor: args.monitor.as_deref().map(proto::x::MonitorSelector::from),
+    });
+
+    curve::CONFIG.get_or_init(|| {
+        let default = curve::Config::default();
+
+        curve::Config {
+            curve,
+            decibels: args.decibels,
+            step: args.step.map(|step| step as f64 / 100.0).unwrap_or(default.step),
+            detent: args.detent.map(|detent| detent as f64 / 100.0).unwrap_or(default.detent),
+        }
+    });
+
+    // Meters are shown only if peaks are going to be probed at all
+    let peak_rate = args.meter_rate
+        .or_else(|| std::env::var("PULSE_PEAK_RATE").ok().and_then(|rate| rate.parse().ok()))
+        .unwrap_or(server::DEFAULT_PEAK_RATE);
+
+    meter::CONFIG.get_or_init(|| {
+        let default = meter::Config::default();
+
+        meter::Config {
+            scale: meter_scale,
+            attack: args.meter_attack.map(|ms| Duration::from_millis(ms as u64)).unwrap_or(default.attack),
+            release: args.meter_release.map(|ms| Duration::from_millis(ms as u64)).unwrap_or(default.release),
+            hold: args.meter_hold.map(|ms| Duration::from_millis(ms as u64)).unwrap_or(default.hold),
+            enabled: peak_rate != 0,
+        }
     });
 
+    let server: server::AudioServerEnum = server::pulse::Pulse::new(server::PeakConfig {
+        per_channel: args.meter_channels,
+        rate: peak_rate,
+        pause_corked: args.meter_pause,
+    }).into();
+
+    #[cfg(feature = "Mock")]
+    let server = match &args.mock {
+        Some(path) => server::mock::Mock::load(path)?.into(),
+        None       => server,
+    };
+
     app.run_async::<app::App>(app::Config {
         width: args.width.unwrap_or(if horizontal { 65 } else { 350 }),
         height: args.height.unwrap_or(if horizontal { 350 } else { 30 }),
@@ -114,18 +380,48 @@ fn main() -> Result<(), Error> {
         horizontal,
         master: args.master,
         show_corked: !args.active_only,
-        per_process: args.per_process,
+        group,
+        group_volume,
+        sort,
+        pins: args.pins,
+        priority: args.priority,
+        pause_meter: args.meter_pause,
+        hide_on_close: args.hide,
+        transition: args.transition.unwrap_or(0),
         userstyle: args.userstyle,
 
         #[cfg(feature = "Accent")]
         accent: args.accent,
 
-        server: server::pulse::Pulse::new().into(),
+        #[cfg(feature = "Mpris")]
+        media: args.media,
+
+        server,
     });
 
     Ok(())
 }
 
+// Activates an action of an already running instance, if there is one.
+fn activate_remote(action: &str, parameter: Option<&gtk::glib::Variant>) -> bool {
+    use gtk::prelude::{ActionGroupExt, ApplicationExt};
+    use gtk::gio::Cancellable;
+
+    let app = relm4::main_application();
+
+    if app.register(Cancellable::NONE).is_err() || !app.is_remote() {
+        return false
+    }
+
+    app.activate_action(action, parameter);
+
+    if let Some(connection) = app.dbus_connection() {
+        let _ = connection.flush_sync(Cancellable::NONE);
+    }
+
+    true
+}
+
 #[allow(unused_variables)]
 fn warning(args: &Args) {
     #[cfg(not(feature = "Wayland"))]
@@ -173,11 +469,19 @@ mod xdg;
 mod server;
 mod app;
 mod anchor;
+mod curve;
+mod desktop;
+mod dismiss;
 mod label;
+mod meter;
 mod proto;
 mod error;
 mod style;
+mod throttle;
 mod widgets;
 
 #[cfg(feature = "Accent")]
 mod accent;
+
+#[cfg(feature = "Mpris")]
+mod mpris;
@@ -0,0 +1,241 @@
+use std::time::{Duration, Instant};
+
+use relm4::once_cell::sync::OnceCell;
+use smallvec::SmallVec;
+
+use crate::error::CLIError;
+
+pub static CONFIG: OnceCell<Config> = const { OnceCell::new() };
+
+// How long the clip indicator stays lit after the signal went over 0 dBFS
+const CLIP_HOLD: Duration = Duration::from_millis(2000);
+
+#[derive(Clone, Copy)]
+pub enum Scale {
+    Linear,
+    Decibel { floor: f64 },
+}
+
+pub struct Config {
+    pub scale: Scale,
+    pub attack: Duration,
+    pub release: Duration,
+    pub hold: Duration,
+    pub enabled: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            scale: Scale::Linear,
+            attack: Duration::ZERO,
+            release: Duration::from_millis(1000),
+            hold: Duration::from_millis(1000),
+            enabled: true,
+        }
+    }
+}
+
+impl Scale {
+    pub fn parse(s: &str, range: u8) -> Result<Self, CLIError> {
+        match s.as_bytes().first().map(u8::to_ascii_lowercase) {
+            Some(b'l') => Ok(Scale::Linear),
+            Some(b'd') => Ok(Scale::Decibel { floor: -(range.max(1) as f64) }),
+            _          => Err(CLIError::MeterScale(s.to_owned())),
+        }
+    }
+
+    /// Converts amplitude into a position on the meter (0.0 - 1.0).
+    pub fn map(&self, amplitude: f64) -> f64 {
+        match *self {
+            Scale::Linear => amplitude.clamp(0.0, 1.0),
+            Scale::Decibel { .. } if amplitude <= 0.0 => 0.0,
+            Scale::Decibel { floor } => {
+                let db = 20.0 * amplitude.log10();
+                ((db - floor) / -floor).clamp(0.0, 1.0)
+            }
+        }
+    }
+
+    /// Converts a position on the meter back into amplitude.
+    pub fn unmap(&self, position: f64) -> f64 {
+        match *self {
+            Scale::Linear => position,
+            Scale::Decibel { .. } if position <= 0.0 => 0.0,
+            Scale::Decibel { floor } => {
+                let db = floor - position * floor;
+                10f64.powf(db / 20.0)
+            }
+        }
+    }
+}
+
+pub struct Meter {
+    config: &'static Config,
+
+    target: f64,
+    level: f64,
+    hold: f64,
+    clipping: bool,
+
+    held_at: Instant,
+    clipped_at: Option<Instant>,
+    updated: Instant,
+}
+
+impl Default for Meter {
+    fn default() -> Self {
+        let now = Instant::now();
+
+        Self {
+            config: CONFIG.get_or_init(Config::default),
+            target: 0.0,
+            level: 0.0,
+            hold: 0.0,
+            clipping: false,
+            held_at: now,
+            clipped_at: None,
+            updated: now,
+        }
+    }
+}
+
+impl Meter {
+    #[inline]
+    pub fn level(&self) -> f64 {
+        self.level
+    }
+
+    #[inline]
+    pub fn hold(&self) -> f64 {
+        self.hold
+    }
+
+    #[inline]
+    pub fn is_clipping(&self) -> bool {
+        self.clipping
+    }
+
+    #[inline]
+    pub fn is_idle(&self) -> bool {
+        self.level == 0.0 && self.hold == 0.0 && self.target == 0.0 && !self.clipping
+    }
+
+    /// Feed a raw peak (linear amplitude) reported by the audio server.
+    pub fn feed(&mut self, peak: f32) {
+        let peak = peak as f64;
+
+        if peak >= 1.0 {
+            self.clipped_at = Some(Instant::now());
+        }
+
+        self.target = self.target.max(self.config.scale.map(peak));
+    }
+
+    /// Scale everything that is currently displayed by the ratio of volume change,
+    /// so that meter reacts immediately and doesn't wait for the audio server.
+    pub fn gain(&mut self, ratio: f64) {
+        let scale = self.config.scale;
+        let apply = |p: f64| scale.map(scale.unmap(p) * ratio);
+
+        self.target = apply(self.target);
+        self.level = apply(self.level);
+        self.hold = apply(self.hold);
+    }
+
+    /// Advance ballistics, returns `true` if anything visible has changed.
+    pub fn tick(&mut self, now: Instant) -> bool {
+        let dt = now.saturating_duration_since(self.updated);
+        self.updated = now;
+
+        let clipping = self.clipped_at.is_some_and(|t| now.saturating_duration_since(t) < CLIP_HOLD);
+
+        if self.level == 0.0 && self.hold == 0.0 && self.target == 0.0 && clipping == self.clipping {
+            return false
+        }
+
+        let before = (self.level, self.hold, self.clipping);
+
+        let fall = match self.config.release.is_zero() {
+            true  => 1.0,
+            false => dt.as_secs_f64() / self.config.release.as_secs_f64(),
+        };
+
+        self.level = match self.target > self.level {
+            true if self.config.attack.is_zero() => self.target,
+            true => {
+                let k = 1.0 - (-dt.as_secs_f64() / self.config.attack.as_secs_f64()).exp();
+                self.level + (self.target - self.level) * k
+            }
+            false => (self.level - fall).max(self.target),
+        };
+
+        self.target = (self.target - fall).max(0.0);
+
+        if self.config.hold.is_zero() {
+            self.hold = 0.0;
+        }
+        else if self.level >= self.hold {
+            self.hold = self.level;
+            self.held_at = now;
+        }
+        else if now.saturating_duration_since(self.held_at) >= self.config.hold {
+            self.hold = (self.hold - fall).max(self.level);
+        }
+
+        self.clipping = clipping;
+
+        before != (self.level, self.hold, self.clipping)
+    }
+}
+
+/// Meter for each audio channel.
+pub struct Meters(SmallVec<[Meter; 2]>);
+
+impl Default for Meters {
+    fn default() -> Self {
+        Self(smallvec::smallvec![Meter::default()])
+    }
+}
+
+impl Meters {
+    pub fn feed(&mut self, peaks: &[f32]) {
+        if self.0.len() < peaks.len() {
+            self.0.resize_with(peaks.len(), Meter::default);
+        }
+
+        for (meter, peak) in self.0.iter_mut().zip(peaks) {
+            meter.feed(*peak);
+        }
+    }
+
+    pub fn gain(&mut self, ratio: f64) {
+        self.0.iter_mut().for_each(|meter| meter.gain(ratio));
+    }
+
+    pub fn tick(&mut self, now: Instant) -> bool {
+        let mut changed = false;
+
+        for meter in self.0.iter_mut() {
+            changed |= meter.tick(now);
+        }
+
+        changed
+    }
+
+    pub fn is_clipping(&self) -> bool {
+        self.0.iter().any(Meter::is_clipping)
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.0.iter().all(Meter::is_idle)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.0.first().is_some_and(|meter| meter.config.enabled)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.0.iter().map(|meter| (meter.level(), meter.hold()))
+    }
+}
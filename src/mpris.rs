@@ -0,0 +1,209 @@
+//! Media players that can be controlled over the session bus with MPRIS.
+//! https://specifications.freedesktop.org/mpris-spec/latest/
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use futures_util::StreamExt;
+use tokio::sync::OnceCell;
+use zbus::fdo::DBusProxy;
+use zbus::names::BusName;
+use zbus::zvariant::OwnedValue;
+
+use crate::app::CommandMessage;
+use crate::error::{Error, ZbusError};
+
+const PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+static CONNECTION: OnceCell<zbus::Connection> = OnceCell::const_new();
+
+#[zbus::proxy(
+    default_path = "/org/mpris/MediaPlayer2",
+    interface = "org.mpris.MediaPlayer2",
+    async_name = "Application"
+)]
+pub trait Application {
+    #[zbus(property)]
+    fn desktop_entry(&self) -> zbus::Result<String>;
+}
+
+#[zbus::proxy(
+    default_path = "/org/mpris/MediaPlayer2",
+    interface = "org.mpris.MediaPlayer2.Player",
+    async_name = "Player"
+)]
+pub trait Player {
+    fn play_pause(&self) -> zbus::Result<()>;
+    fn next(&self) -> zbus::Result<()>;
+    fn previous(&self) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn metadata(&self) -> zbus::Result<HashMap<String, OwnedValue>>;
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> zbus::Result<bool>;
+}
+
+/// Player on the bus and what is needed to find its audio clients.
+#[derive(Debug, Clone)]
+pub struct Media {
+    pub bus: Arc<str>,
+    pub pid: Option<u32>,
+    /// Name of the desktop entry without `.desktop`
+    pub entry: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Track {
+    pub title: String,
+    pub playing: bool,
+    pub can_next: bool,
+    pub can_previous: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Action {
+    PlayPause,
+    Next,
+    Previous,
+}
+
+#[derive(Debug)]
+pub enum MediaMessage {
+    Appeared(Media),
+    Track(Arc<str>, Track),
+    Vanished(Arc<str>),
+}
+
+impl From<MediaMessage> for CommandMessage {
+    fn from(msg: MediaMessage) -> Self {
+        CommandMessage::Media(msg)
+    }
+}
+
+async fn connection() -> Result<&'static zbus::Connection, Error> {
+    CONNECTION.get_or_try_init(|| async {
+        zbus::Connection::session().await
+            .map_err(|e| ZbusError::Connect { e }.into())
+    }).await
+}
+
+/// Reports players as they come and go, along with their tracks.
+pub async fn watch(sender: relm4::Sender<CommandMessage>) -> Result<(), Error> {
+    let connection = connection().await?;
+
+    let dbus = DBusProxy::new(connection).await
+        .map_err(|e| ZbusError::Proxy { e })?;
+
+    let mut owners = dbus.receive_name_owner_changed().await
+        .map_err(|e| ZbusError::Proxy { e })?;
+
+    let names = dbus.list_names().await
+        .map_err(|e| ZbusError::Call { e: e.into(), method: "ListNames" })?;
+
+    for name in names.into_iter().filter(|name| name.starts_with(PREFIX)) {
+        tokio::spawn(follow(dbus.clone(), name.to_string(), sender.clone()));
+    }
+
+    while let Some(signal) = owners.next().await {
+        let Ok(args) = signal.args() else { continue };
+
+        if !args.name().starts_with(PREFIX) {
+            continue
+        }
+
+        // Player that changed its owner is still followed by the same name
+        match (args.old_owner().is_some(), args.new_owner().is_some()) {
+            (false, true) => { tokio::spawn(follow(dbus.clone(), args.name().to_string(), sender.clone())); },
+            (true, false) => sender.emit(MediaMessage::Vanished(args.name().as_str().into()).into()),
+            _ => {},
+        }
+    }
+
+    Ok(())
+}
+
+// Reports a single player until it leaves the bus
+async fn follow(dbus: DBusProxy<'static>, name: String, sender: relm4::Sender<CommandMessage>) -> Result<(), Error> {
+    let connection = connection().await?;
+    let bus: Arc<str> = name.as_str().into();
+
+    let pid = match BusName::try_from(name.as_str()) {
+        Ok(name) => dbus.get_connection_unix_process_id(name).await.ok(),
+        Err(_)   => None,
+    };
+
+    let application = Application::builder(connection)
+        .destination(name.clone())
+        .map_err(|e| ZbusError::Proxy { e })?
+        .build().await
+        .map_err(|e| ZbusError::Proxy { e })?;
+
+    let entry = application.desktop_entry().await.ok()
+        .filter(|entry| !entry.is_empty());
+
+    sender.emit(MediaMessage::Appeared(Media { bus: bus.clone(), pid, entry }).into());
+
+    let player = Player::builder(connection)
+        .destination(name)
+        .map_err(|e| ZbusError::Proxy { e })?
+        .build().await
+        .map_err(|e| ZbusError::Proxy { e })?;
+
+    let mut status = player.receive_playback_status_changed().await;
+    let mut metadata = player.receive_metadata_changed().await;
+    let mut owner = player.inner().receive_owner_changed().await
+        .map_err(|e| ZbusError::Proxy { e })?;
+
+    loop {
+        sender.emit(MediaMessage::Track(bus.clone(), track(&player).await).into());
+
+        tokio::select! {
+            Some(_) = status.next() => {},
+            Some(_) = metadata.next() => {},
+            owner = owner.next() => if !matches!(owner, Some(Some(_))) {
+                break
+            },
+            else => break,
+        }
+    }
+
+    Ok(())
+}
+
+async fn track(player: &Player<'_>) -> Track {
+    let title = player.metadata().await.ok()
+        .and_then(|metadata| metadata.get("xesam:title").and_then(|title| title.downcast_ref::<String>().ok()))
+        .unwrap_or_default();
+
+    Track {
+        title,
+        playing: player.playback_status().await.is_ok_and(|status| status == "Playing"),
+        can_next: player.can_go_next().await.unwrap_or_default(),
+        can_previous: player.can_go_previous().await.unwrap_or_default(),
+    }
+}
+
+pub async fn control(bus: &str, action: Action) -> Result<(), Error> {
+    let connection = connection().await?;
+
+    let player = Player::builder(connection)
+        .destination(bus)
+        .map_err(|e| ZbusError::Proxy { e })?
+        .build().await
+        .map_err(|e| ZbusError::Proxy { e })?;
+
+    let (result, method) = match action {
+        Action::PlayPause => (player.play_pause().await, "PlayPause"),
+        Action::Next      => (player.next().await, "Next"),
+        Action::Previous  => (player.previous().await, "Previous"),
+    };
+
+    result.map_err(|e| ZbusError::Call { e, method }.into())
+}
@@ -1,33 +1,208 @@
 use relm4::component::AsyncComponent;
 
+use gtk::prelude::{Cast, DisplayExt, GtkWindowExt, ListModelExt, MonitorExt, NativeExt, ObjectExt, SurfaceExt, WidgetExt};
+
 use gtk4_layer_shell::{Edge, KeyboardMode, Layer, LayerShell};
 
-use crate::{anchor::Anchor, app::App, label, warnln};
+use crate::{anchor::{Anchor, Placement, Rect}, app::{App, WMConfig}, error::CLIError, label, warnln};
+
+pub struct LayerShellConfig {
+    pub layer: Layer,
+    pub namespace: String,
+    pub output: Option<String>,
+    pub exclusive_zone: Option<i32>,
+    pub keyboard: Option<KeyboardMode>,
+}
+
+impl Default for LayerShellConfig {
+    fn default() -> Self {
+        Self {
+            layer: Layer::Top,
+            namespace: "volume-mixer".to_owned(),
+            output: None,
+            exclusive_zone: None,
+            keyboard: None,
+        }
+    }
+}
+
+pub fn parse_layer(s: &str) -> Result<Layer, CLIError> {
+    match s.as_bytes().first().map(u8::to_ascii_lowercase) {
+        Some(b'o') => Ok(Layer::Overlay),
+        Some(b't') => Ok(Layer::Top),
+        Some(b'b') => Ok(Layer::Bottom),
+        _          => Err(CLIError::Layer(s.to_owned())),
+    }
+}
+
+pub fn parse_keyboard_mode(s: &str) -> Result<KeyboardMode, CLIError> {
+    match s.as_bytes().first().map(u8::to_ascii_lowercase) {
+        Some(b'n') => Ok(KeyboardMode::None),
+        Some(b'e') => Ok(KeyboardMode::Exclusive),
+        Some(b'o') => Ok(KeyboardMode::OnDemand),
+        _          => Err(CLIError::KeyboardMode(s.to_owned())),
+    }
+}
 
 impl App where Self: AsyncComponent {
-    pub fn init_wayland(window: &<Self as AsyncComponent>::Root, anchors: Anchor, margins: &[i32], focusable: bool) {
+    pub fn init_wayland(window: &<Self as AsyncComponent>::Root, config: &WMConfig) {
         if !gtk4_layer_shell::is_supported() {
             warnln!("You're using Wayland, but your compositor doesn't support {} protocol.", label::LAYER_SHELL_PROTOCOL);
             return
         }
 
+        let layer_shell = &config.layer_shell;
+
         window.init_layer_shell();
-        window.set_layer(Layer::Top);
-        window.set_namespace("volume-mixer");
+        window.set_layer(layer_shell.layer);
+        window.set_namespace(&layer_shell.namespace);
 
-        if focusable {
-            window.set_keyboard_mode(KeyboardMode::OnDemand);
+        if let Some(output) = &layer_shell.output {
+            match find_monitor(window, output) {
+                Some(monitor) => window.set_monitor(&monitor),
+                None => warnln!("Output '{output}' wasn't found, compositor will pick one instead."),
+            }
         }
 
-        for (i, anchor) in anchors.iter().enumerate() {
+        if let Some(zone) = layer_shell.exclusive_zone {
+            window.set_exclusive_zone(zone);
+        }
+
+        let dismiss = config.dismiss;
+
+        // Escape only reaches the window once it's focused, other applications keep the keyboard until then
+        match layer_shell.keyboard {
+            Some(mode) => window.set_keyboard_mode(mode),
+            None if config.close_after != 0 || !dismiss.is_empty() => window.set_keyboard_mode(KeyboardMode::OnDemand),
+            None => {},
+        }
+
+        match config.placement {
+            Placement::Relative(target) => {
+                return Self::place_relative(window, target, layer_shell.output.is_none())
+            }
+            Placement::Pointer => {
+                warnln!("Wayland doesn't expose pointer position to clients, anchors will be used instead.");
+            }
+            Placement::Anchor => {},
+        }
+
+        for (i, anchor) in config.anchors.iter().enumerate() {
             let edge = anchor.try_into().unwrap();
 
             window.set_anchor(edge, true);
-            window.set_margin(edge, *margins.get(i).unwrap_or(&0));
+            window.set_margin(edge, *config.margins.get(i).unwrap_or(&0));
+        }
+    }
+
+    // Layer surfaces can't be positioned directly, so window is anchored
+    // to the top left corner of the monitor and moved with margins instead.
+    fn place_relative(window: &<Self as AsyncComponent>::Root, target: Rect, pick_monitor: bool) {
+        let monitor = match pick_monitor {
+            true  => monitor_at(window, target.x + target.width / 2, target.y + target.height / 2),
+            false => window.monitor(),
+        };
+
+        if pick_monitor {
+            if let Some(monitor) = &monitor {
+                window.set_monitor(monitor);
+            }
+        }
+
+        let screen = match monitor {
+            Some(monitor) => {
+                let geometry = monitor.geometry();
+                Rect { x: geometry.x(), y: geometry.y(), width: geometry.width(), height: geometry.height() }
+            }
+            None => Rect { x: 0, y: 0, width: i32::MAX / 2, height: i32::MAX / 2 },
+        };
+
+        window.set_anchor(Edge::Top, true);
+        window.set_anchor(Edge::Left, true);
+
+        let Some(surface) = window.surface() else { return };
+        let weak = window.downgrade();
+
+        surface.connect_layout(move |_, width, height| {
+            let Some(window) = weak.upgrade() else { return };
+
+            let (x, y) = target.popup((width as u32, height as u32), screen);
+
+            window.set_margin(Edge::Left, x - screen.x);
+            window.set_margin(Edge::Top, y - screen.y);
+        });
+    }
+}
+
+impl App where Self: AsyncComponent {
+    // Transparent surface that covers the monitor underneath the window to catch clicks outside of it.
+    pub fn init_catcher(window: &<Self as AsyncComponent>::Root, config: &WMConfig, on_click: impl Fn() + 'static) -> gtk::Window {
+        let layer_shell = &config.layer_shell;
+
+        let catcher = gtk::Window::builder()
+            .css_classes(["catcher"])
+            .decorated(false)
+            .build();
+
+        catcher.init_layer_shell();
+        catcher.set_layer(layer_shell.layer);
+        catcher.set_namespace(&format!("{}-catcher", layer_shell.namespace));
+        catcher.set_exclusive_zone(-1);
+        catcher.set_keyboard_mode(KeyboardMode::None);
+
+        for edge in [Edge::Top, Edge::Left, Edge::Bottom, Edge::Right] {
+            catcher.set_anchor(edge, true);
         }
+
+        let gesture = gtk::GestureClick::builder().button(0).build();
+        gesture.connect_pressed(move |_, _, _, _| on_click());
+        catcher.add_controller(gesture);
+
+        window.connect_hide({
+            let catcher = catcher.clone();
+            move |_| catcher.set_visible(false)
+        });
+
+        window.connect_destroy({
+            let catcher = catcher.clone();
+            move |_| catcher.destroy()
+        });
+
+        catcher
+    }
+
+    // Surfaces on the same layer are stacked in the order they were mapped,
+    // so catcher has to be mapped before the window and not from its `show` handlers.
+    pub fn show_catcher(window: &<Self as AsyncComponent>::Root, catcher: &gtk::Window) {
+        if let Some(monitor) = window.monitor() {
+            catcher.set_monitor(&monitor);
+        }
+
+        catcher.set_visible(true);
     }
 }
 
+fn monitor_at(window: &gtk::Window, x: i32, y: i32) -> Option<gtk::gdk::Monitor> {
+    let monitors = window.display().monitors();
+
+    (0..monitors.n_items())
+        .filter_map(|i| monitors.item(i))
+        .filter_map(|item| item.downcast::<gtk::gdk::Monitor>().ok())
+        .find(|monitor| {
+            let geometry = monitor.geometry();
+            Rect { x: geometry.x(), y: geometry.y(), width: geometry.width(), height: geometry.height() }.contains(x, y)
+        })
+}
+
+fn find_monitor(window: &gtk::Window, connector: &str) -> Option<gtk::gdk::Monitor> {
+    let monitors = window.display().monitors();
+
+    (0..monitors.n_items())
+        .filter_map(|i| monitors.item(i))
+        .filter_map(|item| item.downcast::<gtk::gdk::Monitor>().ok())
+        .find(|monitor| monitor.connector().is_some_and(|name| name.eq_ignore_ascii_case(connector)))
+}
+
 #[cfg(feature = "Wayland")]
 impl TryFrom<Anchor> for Edge {
     type Error = ();
@@ -42,4 +217,3 @@ impl TryFrom<Anchor> for Edge {
         }
     }
 }
-
@@ -8,64 +8,256 @@ use gdk_x11::{X11Surface, X11Display};
 
 use x11rb::connection::Connection;
 use x11rb::errors::ReplyError;
-use x11rb::protocol::xinerama::get_screen_size;
-use x11rb::protocol::xproto::{PropMode, AtomEnum, ClientMessageEvent, CLIENT_MESSAGE_EVENT, EventMask, ConnectionExt, ConfigureWindowAux};
+use x11rb::protocol::randr::ConnectionExt as _;
+use x11rb::protocol::xinerama::ConnectionExt as _;
+use x11rb::protocol::xproto::{PropMode, AtomEnum, ClientMessageEvent, CLIENT_MESSAGE_EVENT, EventMask, ConnectionExt, ConfigureWindowAux, Window};
 use x11rb::x11_utils::Serialize;
 
-use crate::anchor::Anchor;
-use crate::app::App;
+use crate::anchor::{Placement, Rect};
+use crate::app::{App, WMConfig};
+use crate::error::{Error, X11Error};
+use crate::warnln;
+
+pub enum MonitorSelector {
+    Index(usize),
+    Name(String),
+    Pointer,
+    Focused,
+}
+
+impl From<&str> for MonitorSelector {
+    fn from(s: &str) -> Self {
+        if let Ok(i) = s.parse() {
+            return MonitorSelector::Index(i)
+        }
+
+        match s.to_ascii_lowercase().as_str() {
+            "pointer" => MonitorSelector::Pointer,
+            "focused" => MonitorSelector::Focused,
+            _         => MonitorSelector::Name(s.to_owned()),
+        }
+    }
+}
+
+struct Monitor {
+    name: Option<String>,
+    primary: bool,
+    rect: Rect,
+}
 
 impl App where Self: AsyncComponent {
-    pub fn realize_x11(window: &<Self as AsyncComponent>::Root, anchors: Anchor, margins: Vec<i32>) {
-        let surface = window.surface().unwrap();
+    pub fn realize_x11(window: &<Self as AsyncComponent>::Root, config: &'static WMConfig) {
+        if let Err(e) = Self::init_x11(window, config) {
+            warnln!("{e}\nWindow placement will be left to the window manager.");
+        }
+    }
+
+    fn init_x11(window: &<Self as AsyncComponent>::Root, config: &'static WMConfig) -> Result<(), Error> {
+        let Some(surface) = window.surface() else {
+            return Ok(())
+        };
 
         let Ok(xsurface) = surface.downcast::<X11Surface>() else {
-            return
+            return Ok(())
         };
 
         let Ok(xdisplay) = window.display().downcast::<X11Display>() else {
-            return
+            return Ok(())
         };
 
-        let (conn, _) = x11rb::connect(None).expect("connecting to X11");
-        let atoms = AtomCollection::new(&conn).unwrap().reply().expect("baking atomic cookie");
+        let (conn, _) = x11rb::connect(None).map_err(X11Error::Connect)?;
+        let atoms = intern_atoms(&conn)?;
 
         let conn = Rc::new(conn);
 
         let xid = xsurface.xid() as u32;
 
-        set_wm_properties(conn.as_ref(), atoms, xid).expect("setting WM properties");
+        if let Err(e) = set_wm_properties(conn.as_ref(), atoms, xid) {
+            warnln!("{e}");
+        }
+
+        let screen_num = xdisplay.screen().screen_number() as usize;
+        let Some(screen) = conn.setup().roots.get(screen_num) else {
+            return Ok(())
+        };
 
-        let screen_num = xdisplay.screen().screen_number() as u32;
-        let screen = get_screen_size(conn.as_ref(), xid, screen_num).unwrap().reply().expect("collecting screen info");
+        let (root, screen_width, screen_height) = (screen.root, screen.width_in_pixels, screen.height_in_pixels);
+
+        let placement = resolve_placement(conn.as_ref(), root, config.placement);
+        let monitor = target_monitor(conn.as_ref(), atoms, root, (screen_width, screen_height), config.monitor.as_ref(), placement);
 
         window.connect_map({
             let conn = conn.clone();
 
             move |_| { // Place window off-screen while initializing
-                let config = ConfigureWindowAux::new().x(screen.width as i32).y(screen.height as i32);
-                conn.configure_window(xid, &config).unwrap().check().expect("hiding window offscreen");
+                if let Err(e) = move_window(conn.as_ref(), xid, screen_width as i32, screen_height as i32) {
+                    warnln!("{e}");
+                }
 
-                add_wm_states(conn.as_ref(), atoms, xid).expect("updating _NET_WM_STATE");
+                if let Err(e) = add_wm_states(conn.as_ref(), atoms, xid) {
+                    warnln!("{e}");
+                }
             }
         });
 
         xsurface.connect_layout({
             move |_, width, height| {
-                let (x, y) = anchors.position(&margins,
-                                              (screen.width, screen.height),
-                                              (width as u32, height as u32));
-
-                let config = ConfigureWindowAux::new().x(x).y(y);
-                conn.configure_window(xid, &config).unwrap().check().expect("moving window with `xcb_configure_window`");
+                let (x, y) = match placement {
+                    Placement::Relative(target) => target.popup((width as u32, height as u32), monitor),
+                    _ => {
+                        let (x, y) = config.anchors.position(&config.margins,
+                                                             (monitor.width as u32, monitor.height as u32),
+                                                             (width as u32, height as u32));
+                        (monitor.x + x, monitor.y + y)
+                    }
+                };
+
+                if let Err(e) = move_window(conn.as_ref(), xid, x, y) {
+                    warnln!("{e}");
+                }
             }
         });
+
+        Ok(())
     }
 }
 
+fn intern_atoms(conn: &impl Connection) -> Result<AtomCollection, X11Error> {
+    AtomCollection::new(conn)
+        .map_err(ReplyError::from)
+        .and_then(|cookie| cookie.reply())
+        .map_err(X11Error::Atoms)
+}
+
+fn move_window(conn: &impl Connection, xid: u32, x: i32, y: i32) -> Result<(), X11Error> {
+    let config = ConfigureWindowAux::new().x(x).y(y);
+
+    conn.configure_window(xid, &config)
+        .map_err(ReplyError::from)
+        .and_then(|cookie| cookie.check())
+        .map_err(X11Error::Move)
+}
+
+// Pointer position is known only once, so it's turned into a point to open next to.
+fn resolve_placement(conn: &impl Connection, root: Window, placement: Placement) -> Placement {
+    match placement {
+        Placement::Pointer => match pointer_position(conn, root) {
+            Ok((x, y)) => Placement::Relative(Rect { x, y, width: 0, height: 0 }),
+            Err(e) => {
+                warnln!("{e}\nAnchors will be used instead.");
+                Placement::Anchor
+            }
+        },
+        placement => placement,
+    }
+}
+
+fn target_monitor(conn: &impl Connection, atoms: AtomCollection, root: Window, screen: (u16, u16), selector: Option<&MonitorSelector>, placement: Placement) -> Rect {
+    let monitors = monitors(conn, root);
+
+    let monitor = match (selector, placement) {
+        (Some(selector), _) => select_monitor(conn, atoms, root, &monitors, selector),
+        (None, Placement::Relative(target)) => {
+            monitor_at(&monitors, target.x + target.width / 2, target.y + target.height / 2)
+        }
+        (None, _) => None,
+    };
+
+    monitor
+        .or_else(|| monitors.iter().find(|monitor| monitor.primary))
+        .or(monitors.first())
+        .map(|monitor| monitor.rect)
+        .unwrap_or(Rect { x: 0, y: 0, width: screen.0 as i32, height: screen.1 as i32 })
+}
+
+// Monitors from RandR 1.5, with a fallback to Xinerama for older servers,
+// which will be missing the names.
+fn monitors(conn: &impl Connection, root: Window) -> Vec<Monitor> {
+    let randr = conn.randr_query_version(1, 5).ok()
+        .and_then(|cookie| cookie.reply().ok())
+        .filter(|version| (version.major_version, version.minor_version) >= (1, 5))
+        .and_then(|_| conn.randr_get_monitors(root, true).ok())
+        .and_then(|cookie| cookie.reply().ok());
+
+    if let Some(reply) = randr {
+        return reply.monitors.into_iter()
+            .map(|info| Monitor {
+                name: conn.get_atom_name(info.name).ok()
+                    .and_then(|cookie| cookie.reply().ok())
+                    .map(|reply| String::from_utf8_lossy(&reply.name).into_owned()),
+                primary: info.primary,
+                rect: Rect { x: info.x as i32, y: info.y as i32, width: info.width as i32, height: info.height as i32 },
+            })
+            .collect()
+    }
+
+    conn.xinerama_query_screens().ok()
+        .and_then(|cookie| cookie.reply().ok())
+        .map(|reply| reply.screen_info.into_iter()
+            .enumerate()
+            .map(|(i, info)| Monitor {
+                name: None,
+                primary: i == 0,
+                rect: Rect { x: info.x_org as i32, y: info.y_org as i32, width: info.width as i32, height: info.height as i32 },
+            })
+            .collect())
+        .unwrap_or_default()
+}
+
+fn select_monitor<'a>(conn: &impl Connection, atoms: AtomCollection, root: Window, monitors: &'a [Monitor], selector: &MonitorSelector) -> Option<&'a Monitor> {
+    let monitor = match selector {
+        MonitorSelector::Index(i) => monitors.get(*i),
+        MonitorSelector::Name(name) => monitors.iter().find(|monitor| {
+            monitor.name.as_ref().is_some_and(|n| n.eq_ignore_ascii_case(name))
+        }),
+        MonitorSelector::Pointer => pointer_position(conn, root).ok()
+            .and_then(|(x, y)| monitor_at(monitors, x, y)),
+        MonitorSelector::Focused => focused_window_center(conn, atoms, root)
+            .and_then(|(x, y)| monitor_at(monitors, x, y)),
+    };
+
+    if monitor.is_none() {
+        match selector {
+            MonitorSelector::Index(i)   => warnln!("Monitor #{i} wasn't found, primary will be used instead."),
+            MonitorSelector::Name(name) => warnln!("Monitor '{name}' wasn't found, primary will be used instead."),
+            MonitorSelector::Pointer    => warnln!("Couldn't find a monitor under the pointer, primary will be used instead."),
+            MonitorSelector::Focused    => warnln!("Couldn't find a focused monitor, primary will be used instead."),
+        }
+    }
+
+    monitor
+}
+
+fn monitor_at(monitors: &[Monitor], x: i32, y: i32) -> Option<&Monitor> {
+    monitors.iter().find(|monitor| monitor.rect.contains(x, y))
+}
+
+fn pointer_position(conn: &impl Connection, root: Window) -> Result<(i32, i32), X11Error> {
+    let pointer = conn.query_pointer(root)
+        .map_err(ReplyError::from)
+        .and_then(|cookie| cookie.reply())
+        .map_err(X11Error::Pointer)?;
+
+    Ok((pointer.root_x as i32, pointer.root_y as i32))
+}
+
+fn focused_window_center(conn: &impl Connection, atoms: AtomCollection, root: Window) -> Option<(i32, i32)> {
+    let active = conn.get_property(false, root, atoms._NET_ACTIVE_WINDOW, AtomEnum::WINDOW, 0, 1).ok()?.reply().ok()?;
+    let window = active.value32()?.next().filter(|&window| window != 0)?;
+
+    let geometry = conn.get_geometry(window).ok()?.reply().ok()?;
+    let origin = conn.translate_coordinates(window, root, 0, 0).ok()?.reply().ok()?;
+
+    Some((origin.dst_x as i32 + geometry.width as i32 / 2, origin.dst_y as i32 + geometry.height as i32 / 2))
+}
+
 // Specification:
 // https://specifications.freedesktop.org/wm-spec/1.5/ar01s04.html
-fn set_wm_properties(conn: &impl Connection, atoms: AtomCollection, xid: u32) -> Result<(), ReplyError> {
+fn set_wm_properties(conn: &impl Connection, atoms: AtomCollection, xid: u32) -> Result<(), X11Error> {
+    change_wm_properties(conn, atoms, xid).map_err(X11Error::Properties)
+}
+
+fn change_wm_properties(conn: &impl Connection, atoms: AtomCollection, xid: u32) -> Result<(), ReplyError> {
     use x11rb::wrapper::ConnectionExt;
 
     conn.change_property32(PropMode::REPLACE,
@@ -89,9 +281,9 @@ fn set_wm_properties(conn: &impl Connection, atoms: AtomCollection, xid: u32) ->
     Ok(())
 }
 
-fn add_wm_states(conn: &impl Connection, atoms: AtomCollection, xid: u32) -> Result<(), ReplyError> {
-    add_wm_state(conn, xid, atoms, atoms._NET_WM_STATE_ABOVE, atoms._NET_WM_STATE_STICKY)?;
-    add_wm_state(conn, xid, atoms, atoms._NET_WM_STATE_SKIP_TASKBAR, atoms._NET_WM_STATE_SKIP_PAGER)?;
+fn add_wm_states(conn: &impl Connection, atoms: AtomCollection, xid: u32) -> Result<(), X11Error> {
+    add_wm_state(conn, xid, atoms, atoms._NET_WM_STATE_ABOVE, atoms._NET_WM_STATE_STICKY).map_err(X11Error::State)?;
+    add_wm_state(conn, xid, atoms, atoms._NET_WM_STATE_SKIP_TASKBAR, atoms._NET_WM_STATE_SKIP_PAGER).map_err(X11Error::State)?;
 
     Ok(())
 }
@@ -118,6 +310,8 @@ fn add_wm_state(conn: &impl Connection, xid: u32, atoms: AtomCollection, s1: u32
 
 x11rb::atom_manager! {
     pub AtomCollection: AtomCollectionCookie {
+        _NET_ACTIVE_WINDOW,
+
         _NET_WM_STATE,
         _NET_WM_STATE_ABOVE,
         _NET_WM_STATE_SKIP_PAGER,
@@ -134,3 +328,291 @@ x11rb::atom_manager! {
         _NET_WM_ACTION_ABOVE,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::io::IoSlice;
+
+    use x11rb::connection::{Connection, RawEventAndSeqNumber, ReplyOrError, RequestConnection, RequestKind, DiscardMode, SequenceNumber, BufWithFds};
+    use x11rb::cookie::{Cookie, CookieWithFds, VoidCookie};
+    use x11rb::errors::{ConnectionError, ParseError, ReplyError, ReplyOrIdError};
+    use x11rb::protocol::{ErrorKind, Event};
+    use x11rb::protocol::xproto::{KeyButMask, QueryPointerReply, Screen, Setup};
+    use x11rb::utils::RawFdContainer;
+    use x11rb::x11_utils::{ExtensionInformation, Serialize, TryParse, TryParseFd};
+
+    use crate::anchor::{Placement, Rect};
+    use crate::error::X11Error;
+
+    use super::*;
+
+    const ROOT: Window = 1;
+    const SCREEN: (u16, u16) = (1920, 1080);
+
+    // Answers requests with queued replies and X11 errors once they run out.
+    // No extensions are present, like on a minimal X server.
+    #[derive(Default)]
+    struct FakeConnection {
+        setup: Setup,
+        replies: RefCell<VecDeque<Vec<u8>>>,
+        disconnected: bool,
+        reject_void: bool,
+    }
+
+    impl FakeConnection {
+        fn new() -> Self {
+            let screen = Screen { root: ROOT, width_in_pixels: SCREEN.0, height_in_pixels: SCREEN.1, ..Default::default() };
+            let setup = Setup { roots: vec![screen], ..Default::default() };
+
+            Self { setup, ..Default::default() }
+        }
+
+        fn disconnected() -> Self {
+            Self { disconnected: true, ..Self::new() }
+        }
+
+        fn rejecting() -> Self {
+            Self { reject_void: true, ..Self::new() }
+        }
+
+        fn reply<R: Serialize>(self, reply: R) -> Self where R::Bytes: AsRef<[u8]> {
+            let mut reply = reply.serialize().as_ref().to_vec();
+            reply.resize(reply.len().max(32), 0);
+
+            self.replies.borrow_mut().push_back(reply);
+            self
+        }
+
+        fn send(&self) -> Result<SequenceNumber, ConnectionError> {
+            match self.disconnected {
+                true  => Err(ConnectionError::IoError(std::io::ErrorKind::BrokenPipe.into())),
+                false => Ok(0),
+            }
+        }
+
+        fn error() -> Vec<u8> {
+            let mut error = vec![0; 32];
+            error[1] = 17; // BadImplementation
+            error
+        }
+    }
+
+    impl RequestConnection for FakeConnection {
+        type Buf = Vec<u8>;
+
+        fn send_request_with_reply<R>(&self, _: &[IoSlice], _: Vec<RawFdContainer>) -> Result<Cookie<'_, Self, R>, ConnectionError>
+        where R: TryParse {
+            Ok(Cookie::new(self, self.send()?))
+        }
+
+        fn send_request_with_reply_with_fds<R>(&self, _: &[IoSlice], _: Vec<RawFdContainer>) -> Result<CookieWithFds<'_, Self, R>, ConnectionError>
+        where R: TryParseFd {
+            Ok(CookieWithFds::new(self, self.send()?))
+        }
+
+        fn send_request_without_reply(&self, _: &[IoSlice], _: Vec<RawFdContainer>) -> Result<VoidCookie<'_, Self>, ConnectionError> {
+            Ok(VoidCookie::new(self, self.send()?))
+        }
+
+        fn discard_reply(&self, _: SequenceNumber, _: RequestKind, _: DiscardMode) {}
+
+        fn prefetch_extension_information(&self, _: &'static str) -> Result<(), ConnectionError> {
+            Ok(())
+        }
+
+        fn extension_information(&self, _: &'static str) -> Result<Option<ExtensionInformation>, ConnectionError> {
+            Ok(None)
+        }
+
+        fn wait_for_reply_or_raw_error(&self, _: SequenceNumber) -> Result<ReplyOrError<Vec<u8>>, ConnectionError> {
+            match self.replies.borrow_mut().pop_front() {
+                Some(reply) => Ok(ReplyOrError::Reply(reply)),
+                None        => Ok(ReplyOrError::Error(Self::error())),
+            }
+        }
+
+        fn wait_for_reply(&self, _: SequenceNumber) -> Result<Option<Vec<u8>>, ConnectionError> {
+            Ok(self.replies.borrow_mut().pop_front())
+        }
+
+        fn wait_for_reply_with_fds_raw(&self, _: SequenceNumber) -> Result<ReplyOrError<BufWithFds<Vec<u8>>, Vec<u8>>, ConnectionError> {
+            Ok(ReplyOrError::Error(Self::error()))
+        }
+
+        fn check_for_raw_error(&self, _: SequenceNumber) -> Result<Option<Vec<u8>>, ConnectionError> {
+            Ok(self.reject_void.then(Self::error))
+        }
+
+        fn prefetch_maximum_request_bytes(&self) {}
+
+        fn maximum_request_bytes(&self) -> usize {
+            u16::MAX as usize * 4
+        }
+
+        fn parse_error(&self, error: &[u8]) -> Result<x11rb::x11_utils::X11Error, ParseError> {
+            Ok(x11rb::x11_utils::X11Error {
+                error_kind: ErrorKind::Implementation,
+                error_code: error[1],
+                sequence: 0,
+                bad_value: 0,
+                minor_opcode: 0,
+                major_opcode: 0,
+                extension_name: None,
+                request_name: None,
+            })
+        }
+
+        fn parse_event(&self, _: &[u8]) -> Result<Event, ParseError> {
+            Err(ParseError::InvalidValue)
+        }
+    }
+
+    impl Connection for FakeConnection {
+        fn wait_for_raw_event_with_sequence(&self) -> Result<RawEventAndSeqNumber<Vec<u8>>, ConnectionError> {
+            Err(ConnectionError::UnknownError)
+        }
+
+        fn poll_for_raw_event_with_sequence(&self) -> Result<Option<RawEventAndSeqNumber<Vec<u8>>>, ConnectionError> {
+            Ok(None)
+        }
+
+        fn flush(&self) -> Result<(), ConnectionError> {
+            Ok(())
+        }
+
+        fn setup(&self) -> &Setup {
+            &self.setup
+        }
+
+        fn generate_id(&self) -> Result<u32, ReplyOrIdError> {
+            Ok(1)
+        }
+    }
+
+    // Atoms are never interned by the fake connection
+    fn atoms() -> AtomCollection {
+        AtomCollection {
+            _NET_ACTIVE_WINDOW: 0,
+
+            _NET_WM_STATE: 0,
+            _NET_WM_STATE_ABOVE: 0,
+            _NET_WM_STATE_SKIP_PAGER: 0,
+            _NET_WM_STATE_SKIP_TASKBAR: 0,
+            _NET_WM_STATE_STICKY: 0,
+
+            _NET_WM_WINDOW_TYPE: 0,
+            _NET_WM_WINDOW_TYPE_UTILITY: 0,
+
+            _NET_WM_BYPASS_COMPOSITOR: 0,
+
+            _NET_WM_ALLOWED_ACTIONS: 0,
+            _NET_WM_ACTION_CLOSE: 0,
+            _NET_WM_ACTION_ABOVE: 0,
+        }
+    }
+
+    fn pointer(x: i16, y: i16) -> QueryPointerReply {
+        QueryPointerReply {
+            same_screen: true,
+            sequence: 0,
+            length: 0,
+            root: ROOT,
+            child: 0,
+            root_x: x,
+            root_y: y,
+            win_x: x,
+            win_y: y,
+            mask: KeyButMask::default(),
+        }
+    }
+
+    fn monitor(name: &str, x: i32, primary: bool) -> Monitor {
+        Monitor {
+            name: Some(name.to_owned()),
+            primary,
+            rect: Rect { x, y: 0, width: 1920, height: 1080 },
+        }
+    }
+
+    #[test]
+    fn lost_connection() {
+        let conn = FakeConnection::disconnected();
+
+        assert!(matches!(intern_atoms(&conn), Err(X11Error::Atoms(ReplyError::ConnectionError(_)))));
+        assert!(matches!(move_window(&conn, 2, 0, 0), Err(X11Error::Move(ReplyError::ConnectionError(_)))));
+        assert!(matches!(pointer_position(&conn, ROOT), Err(X11Error::Pointer(_))));
+    }
+
+    #[test]
+    fn rejected_requests() {
+        let conn = FakeConnection::rejecting();
+        let atoms = atoms();
+
+        assert!(matches!(set_wm_properties(&conn, atoms, 2), Err(X11Error::Properties(ReplyError::X11Error(_)))));
+        assert!(matches!(add_wm_states(&conn, atoms, 2), Err(X11Error::State(ReplyError::X11Error(_)))));
+        assert!(matches!(move_window(&conn, 2, 0, 0), Err(X11Error::Move(ReplyError::X11Error(_)))));
+    }
+
+    #[test]
+    fn accepted_requests() {
+        let conn = FakeConnection::new();
+        let atoms = atoms();
+
+        assert!(set_wm_properties(&conn, atoms, 2).is_ok());
+        assert!(add_wm_states(&conn, atoms, 2).is_ok());
+        assert!(move_window(&conn, 2, 0, 0).is_ok());
+    }
+
+    #[test]
+    fn missing_extensions() {
+        let conn = FakeConnection::new();
+
+        assert!(monitors(&conn, ROOT).is_empty());
+
+        let screen = Rect { x: 0, y: 0, width: SCREEN.0 as i32, height: SCREEN.1 as i32 };
+        let monitor = target_monitor(&conn, atoms(), ROOT, SCREEN, None, Placement::Anchor);
+
+        assert_eq!(monitor, screen);
+    }
+
+    #[test]
+    fn pointer_placement() {
+        let conn = FakeConnection::new().reply(pointer(100, 200));
+        let placement = resolve_placement(&conn, ROOT, Placement::Pointer);
+
+        assert!(placement == Placement::Relative(Rect { x: 100, y: 200, width: 0, height: 0 }));
+
+        // Falls back to anchors when pointer can't be queried
+        let conn = FakeConnection::new();
+        let placement = resolve_placement(&conn, ROOT, Placement::Pointer);
+
+        assert!(placement == Placement::Anchor);
+    }
+
+    #[test]
+    fn monitor_selection() {
+        let conn = FakeConnection::new();
+        let atoms = atoms();
+        let monitors = [monitor("DP-1", 0, false), monitor("HDMI-1", 1920, true)];
+
+        let select = |selector: &MonitorSelector| {
+            select_monitor(&conn, atoms, ROOT, &monitors, selector).map(|monitor| monitor.rect.x)
+        };
+
+        assert_eq!(select(&MonitorSelector::from("hdmi-1")), Some(1920));
+        assert_eq!(select(&MonitorSelector::from("0")), Some(0));
+        assert_eq!(select(&MonitorSelector::from("2")), None);
+        assert_eq!(select(&MonitorSelector::from("eDP-1")), None);
+
+        // Neither pointer, nor focused window are known
+        assert_eq!(select(&MonitorSelector::from("pointer")), None);
+        assert_eq!(select(&MonitorSelector::from("focused")), None);
+
+        let conn = FakeConnection::new().reply(pointer(2000, 10));
+        let monitor = select_monitor(&conn, atoms, ROOT, &monitors, &MonitorSelector::Pointer);
+
+        assert_eq!(monitor.map(|monitor| monitor.rect.x), Some(1920));
+    }
+}
@@ -12,6 +12,22 @@ pub enum Error {
 
     #[error(transparent)]
     Pulse(#[from] PulseError),
+
+    #[cfg(any(feature = "Mock", test))]
+    #[error(transparent)]
+    Mock(#[from] MockError),
+}
+
+impl Error {
+    /// Client that the server refused to change, if the error names one.
+    pub fn client(&self) -> Option<u32> {
+        match self {
+            Error::Pulse(PulseError::Volume(id, _) | PulseError::Mute(id, _) | PulseError::Move(id, ..)) => Some(*id),
+            #[cfg(any(feature = "Mock", test))]
+            Error::Mock(MockError::UnknownClient(id)) => Some(*id),
+            _ => None,
+        }
+    }
 }
 
 impl Debug for Error {
@@ -40,10 +56,42 @@ pub enum PulseError {
     #[error("Audio sink has a port without a name and will be ignored. ID: {0}")]
     NamelessPort(u32),
 
+    #[error("Unable to change volume of the client {0}\n{1}")]
+    Volume(u32, Code),
+
+    #[error("Unable to change mute state of the client {0}\n{1}")]
+    Mute(u32, Code),
+
+    #[error("Unable to switch output to {0}\n{1}")]
+    Output(String, Code),
+
+    #[error("Unable to move the client {0} to {1}\n{2}")]
+    Move(u32, String, Code),
+
     #[error("{0}")]
     Other(Code),
 }
 
+#[cfg(any(feature = "Mock", test))]
+#[derive(Error, Debug)]
+pub enum MockError {
+    #[error("No connection to the mock server")]
+    NotConnected,
+
+    #[cfg(feature = "Mock")]
+    #[error("Unable to read a mock script ({path})\n{e}")]
+    Read { e: std::io::Error, path: std::path::PathBuf },
+
+    #[error("Invalid mock script at line {line}: {reason}")]
+    Parse { line: usize, reason: String },
+
+    #[error("Mock server has no client {0}")]
+    UnknownClient(u32),
+
+    #[error("Mock server has no output {0}")]
+    UnknownOutput(String),
+}
+
 impl From<PAErr> for PulseError {
     fn from(e: PAErr) -> Self {
         use num_traits::FromPrimitive;
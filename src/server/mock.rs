@@ -0,0 +1,853 @@
+//! Audio server that plays a scripted timeline instead of talking to real hardware.
+//!
+//! Every line of a script is an event: time in milliseconds, action and its arguments.
+//! Events at 0 are the initial state of the server, the rest of the timeline starts once the mixer subscribes to changes.
+//! ```text
+//! # ms   action   arguments
+//! 0      output   alsa_output.analog port=analog-output-speaker default
+//! 0      output   alsa_output.hdmi
+//! 0      master   description=Speakers volume=70
+//! 0      new      1 name=Firefox description="Playing Video" volume=50,60
+//! 1500   change   1 volume=80 muted
+//! 2000   peak     1 0.5 0.7
+//! 3000   change   1 corked
+//! 4000   remove   1
+//! 4500   unplug   alsa_output.hdmi
+//! 5000   disconnect
+//! ```
+//! Clients accept `name`, `description`, `icon`, `process`, `binary`, `flatpak`,
+//! `volume` (percent for each channel) and `muted`, `corked` flags (`muted=false` to clear).
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::{Condvar, Mutex};
+use smallvec::SmallVec;
+
+use super::error::{Error, MockError};
+use super::{AudioServer, Kind, Message, MessageClient, MessageOutput, Output, OutputClient, Peaks, Sender, Sink, Volume, VolumeLevels};
+
+const NORMAL: u32 = 0x10000;
+
+/// Request that the mock has received from the mixer.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub enum Call {
+    Volume { ids: Vec<u32>, kind: Kind, levels: VolumeLevels },
+    Mute { ids: Vec<u32>, kind: Kind, flag: bool },
+    Output { name: String, port: Option<String> },
+    Move { ids: Vec<u32>, output: String },
+}
+
+#[derive(Debug, Clone)]
+enum Property {
+    Name(String),
+    Description(String),
+    Icon(String),
+    Process(u32),
+    Binary(String),
+    Flatpak(String),
+    Entry(String),
+    Volume(SmallVec<[u32; 2]>),
+    Muted(bool),
+    Corked(bool),
+}
+
+#[derive(Debug, Clone)]
+enum Action {
+    New(u32, Vec<Property>),
+    Change(u32, Vec<Property>),
+    Remove(u32),
+    Peak(u32, SmallVec<[f32; 2]>),
+    Master(Vec<Property>),
+    Output { output: Output, description: String, default: bool },
+    Unplug(String),
+    Disconnect,
+}
+
+#[derive(Debug, Clone)]
+struct Event {
+    at: Duration,
+    action: Action,
+}
+
+#[derive(Default)]
+struct State {
+    connected: bool,
+    terminated: bool,
+    peaking: bool,
+    plan: Option<Kind>,
+    sender: Option<Sender<Message>>,
+    clients: BTreeMap<u32, OutputClient>,
+    master: Option<OutputClient>,
+    outputs: Vec<Output>,
+    sinks: Vec<Sink>,
+    next_sink: u32,
+}
+
+pub struct Mock {
+    timeline: Vec<Event>,
+    state: Arc<Mutex<State>>,
+    wakeup: Arc<Condvar>,
+    calls: Arc<Mutex<Vec<Call>>>,
+}
+
+impl Mock {
+    #[cfg(feature = "Mock")]
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, MockError> {
+        let path = path.as_ref();
+
+        let script = std::fs::read_to_string(path)
+            .map_err(|e| MockError::Read { e, path: path.to_owned() })?;
+
+        Self::parse(&script)
+    }
+
+    pub fn parse(script: &str) -> Result<Self, MockError> {
+        let mut timeline = script.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+            .map(|(i, line)| parse_event(line).map_err(|reason| MockError::Parse { line: i + 1, reason }))
+            .collect::<Result<Vec<Event>, MockError>>()?;
+
+        timeline.sort_by_key(|event| event.at);
+
+        Ok(Self {
+            timeline,
+            state: Arc::default(),
+            wakeup: Arc::default(),
+            calls: Arc::default(),
+        })
+    }
+
+    /// Every request received so far, in order of arrival.
+    #[allow(dead_code)]
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.lock().clone()
+    }
+
+    fn is_connected(&self) -> bool {
+        self.state.lock().connected
+    }
+
+    fn record(&self, call: Call) {
+        self.calls.lock().push(call);
+    }
+}
+
+impl AudioServer for Mock {
+    fn connect(&self, sender: impl Into<Sender<Message>>) -> Result<(), Error> {
+        let sender: Sender<Message> = sender.into();
+
+        {
+            let mut state = self.state.lock();
+
+            if state.connected {
+                return Err(Error::AlreadyConnected)
+            }
+
+            // Peaks are sent until the mixer pauses them, like Pulse Audio does
+            *state = State {
+                connected: true,
+                peaking: true,
+                sender: Some(sender.clone()),
+                ..State::default()
+            };
+        }
+
+        let (initial, timeline) = self.timeline.split_at(self.timeline.partition_point(|event| event.at.is_zero()));
+
+        let mut state = self.state.lock();
+
+        for event in initial {
+            state.apply(event.action.clone());
+        }
+
+        sender.emit(Message::Ready);
+
+        while !state.terminated && state.plan.is_none() {
+            self.wakeup.wait(&mut state);
+        }
+
+        let start = Instant::now();
+
+        for event in timeline {
+            while !state.terminated && start.elapsed() < event.at {
+                let _ = self.wakeup.wait_until(&mut state, start + event.at);
+            }
+
+            if state.terminated {
+                break
+            }
+
+            state.apply(event.action.clone());
+        }
+
+        while !state.terminated {
+            self.wakeup.wait(&mut state);
+        }
+
+        Ok(())
+    }
+
+    fn disconnect(&self) {
+        let mut state = self.state.lock();
+
+        if state.connected {
+            state.disconnect();
+        }
+
+        self.wakeup.notify_all();
+    }
+
+    async fn request_software(&self, sender: impl Into<Sender<Message>>) -> Result<(), Error> {
+        let sender = sender.into();
+        let state = self.state.lock();
+
+        if !state.connected {
+            return Err(MockError::NotConnected.into())
+        }
+
+        for client in state.clients.values() {
+            sender.emit(Message::from(MessageClient::New(Box::new(client.clone()))));
+        }
+
+        Ok(())
+    }
+
+    async fn request_master(&self, sender: impl Into<Sender<Message>>) -> Result<(), Error> {
+        let sender = sender.into();
+        let state = self.state.lock();
+
+        if !state.connected {
+            return Err(MockError::NotConnected.into())
+        }
+
+        if let Some(master) = &state.master {
+            sender.emit(Message::from(MessageClient::New(Box::new(master.clone()))));
+        }
+
+        if let Some(output) = state.outputs.iter().find(|output| output.master) {
+            sender.emit(Message::from(MessageOutput::Master(output.clone())));
+        }
+
+        Ok(())
+    }
+
+    async fn request_outputs(&self, sender: impl Into<Sender<Message>>) -> Result<(), Error> {
+        let sender = sender.into();
+        let state = self.state.lock();
+
+        if !state.connected {
+            return Err(MockError::NotConnected.into())
+        }
+
+        for output in &state.outputs {
+            sender.emit(Message::from(MessageOutput::New(Output { master: false, ..output.clone() })));
+        }
+
+        for sink in &state.sinks {
+            sender.emit(Message::from(MessageOutput::Sink(sink.clone())));
+        }
+
+        Ok(())
+    }
+
+    async fn subscribe(&self, plan: Kind, sender: impl Into<Sender<Message>>) -> Result<(), Error> {
+        let mut state = self.state.lock();
+
+        if !state.connected {
+            return Err(MockError::NotConnected.into())
+        }
+
+        state.plan = Some(plan);
+        state.sender = Some(sender.into());
+
+        self.wakeup.notify_all();
+
+        Ok(())
+    }
+
+    async fn set_peaking(&self, flag: bool, _: impl Into<Sender<Message>>) -> Result<(), Error> {
+        let mut state = self.state.lock();
+
+        if !state.connected {
+            return Err(MockError::NotConnected.into())
+        }
+
+        state.peaking = flag;
+
+        Ok(())
+    }
+
+    async fn set_volume(&self, ids: impl IntoIterator<Item = u32>, kind: Kind, levels: VolumeLevels) -> Result<(), Error> {
+        let ids: Vec<u32> = ids.into_iter().collect();
+
+        self.record(Call::Volume { ids: ids.clone(), kind, levels: levels.clone() });
+
+        if !self.is_connected() {
+            return Err(MockError::NotConnected.into())
+        }
+
+        let property = Property::Volume(levels.0);
+
+        let mut state = self.state.lock();
+
+        let mut result = Ok(());
+
+        for id in ids {
+            if !state.change(id, kind, std::slice::from_ref(&property)) && result.is_ok() {
+                result = Err(MockError::UnknownClient(id).into());
+            }
+        }
+
+        result
+    }
+
+    async fn set_mute(&self, ids: impl IntoIterator<Item = u32>, kind: Kind, flag: bool) -> Result<(), Error> {
+        let ids: Vec<u32> = ids.into_iter().collect();
+
+        self.record(Call::Mute { ids: ids.clone(), kind, flag });
+
+        if !self.is_connected() {
+            return Err(MockError::NotConnected.into())
+        }
+
+        let mut state = self.state.lock();
+
+        let mut result = Ok(());
+
+        for id in ids {
+            if !state.change(id, kind, &[Property::Muted(flag)]) && result.is_ok() {
+                result = Err(MockError::UnknownClient(id).into());
+            }
+        }
+
+        result
+    }
+
+    async fn set_output_by_name(&self, name: &str, port: Option<&str>) -> Result<(), Error> {
+        self.record(Call::Output { name: name.to_owned(), port: port.map(str::to_owned) });
+
+        if !self.is_connected() {
+            return Err(MockError::NotConnected.into())
+        }
+
+        let mut state = self.state.lock();
+
+        let Some(i) = state.outputs.iter().position(|output| output.name == name && port.is_none_or(|port| output.port == port)) else {
+            return Err(MockError::UnknownOutput(name.to_owned()).into())
+        };
+
+        state.set_default(i);
+
+        Ok(())
+    }
+
+    async fn move_clients(&self, ids: impl IntoIterator<Item = u32>, output: &str) -> Result<(), Error> {
+        let ids: Vec<u32> = ids.into_iter().collect();
+
+        self.record(Call::Move { ids: ids.clone(), output: output.to_owned() });
+
+        if !self.is_connected() {
+            return Err(MockError::NotConnected.into())
+        }
+
+        let state = self.state.lock();
+
+        if !state.sinks.iter().any(|sink| sink.name == output) {
+            return Err(MockError::UnknownOutput(output.to_owned()).into())
+        }
+
+        match ids.into_iter().find(|id| !state.clients.contains_key(id)) {
+            Some(id) => Err(MockError::UnknownClient(id).into()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl State {
+    fn emit(&self, kind: Kind, message: impl Into<Message>) {
+        let subscribed = self.plan.is_some_and(|plan| plan.contains(kind));
+
+        if let Some(sender) = self.sender.as_ref().filter(|_| subscribed) {
+            sender.emit(message.into());
+        }
+    }
+
+    fn apply(&mut self, action: Action) {
+        match action {
+            Action::New(id, properties) => {
+                let mut client = software_client(id);
+                apply_properties(&mut client, &properties);
+
+                self.emit(Kind::Software, MessageClient::New(Box::new(client.clone())));
+                self.clients.insert(id, client);
+            },
+            Action::Change(id, properties) => { self.change(id, Kind::Software, &properties); },
+            Action::Remove(id) => {
+                if self.clients.remove(&id).is_some() {
+                    self.emit(Kind::Software, MessageClient::Removed(id));
+                }
+            },
+            Action::Peak(id, peaks) => {
+                if self.peaking && self.clients.contains_key(&id) {
+                    self.emit(Kind::Software, MessageClient::Peak(id, Peaks(peaks)));
+                }
+            },
+            Action::Master(properties) => match self.master.is_some() {
+                true  => { self.change(0, Kind::Hardware, &properties); },
+                false => {
+                    let mut master = master_client();
+                    apply_properties(&mut master, &properties);
+
+                    self.emit(Kind::Hardware, MessageClient::New(Box::new(master.clone())));
+                    self.master = Some(master);
+                }
+            },
+            Action::Output { output, description, default } => {
+                // Every port of an output belongs to the same sink
+                if !self.sinks.iter().any(|sink| sink.name == output.name) {
+                    let sink = Sink { id: self.next_sink, name: output.name.clone(), description };
+
+                    self.next_sink += 1;
+                    self.emit(Kind::Software, MessageOutput::Sink(sink.clone()));
+                    self.sinks.push(sink);
+                }
+
+                self.outputs.push(output);
+
+                if default {
+                    self.set_default(self.outputs.len() - 1);
+                }
+            },
+            Action::Unplug(name) => {
+                self.outputs.retain(|output| output.name != name);
+
+                if let Some(i) = self.sinks.iter().position(|sink| sink.name == name) {
+                    let sink = self.sinks.remove(i);
+                    self.emit(Kind::Software, MessageOutput::SinkRemoved(sink.id));
+                }
+            },
+            Action::Disconnect => self.disconnect(),
+        }
+    }
+
+    /// Returns false if there is no such client.
+    fn change(&mut self, id: u32, kind: Kind, properties: &[Property]) -> bool {
+        let client = match kind.contains(Kind::Hardware) {
+            true  => self.master.as_mut().filter(|master| master.id == id),
+            false => self.clients.get_mut(&id),
+        };
+
+        let Some(client) = client else { return false };
+
+        apply_properties(client, properties);
+
+        let message = MessageClient::Changed(Box::new(client.clone()));
+        self.emit(kind, message);
+
+        true
+    }
+
+    fn set_default(&mut self, i: usize) {
+        for (j, output) in self.outputs.iter_mut().enumerate() {
+            output.master = i == j;
+        }
+
+        self.emit(Kind::Hardware, MessageOutput::Master(self.outputs[i].clone()));
+    }
+
+    fn disconnect(&mut self) {
+        if let Some(sender) = self.sender.take() {
+            sender.emit(Message::Disconnected(None));
+        }
+
+        self.connected = false;
+        self.terminated = true;
+        self.plan = None;
+    }
+}
+
+impl Volume {
+    fn mock_linear(&self) -> f64 {
+        let max = *self.levels.iter().max().unwrap_or(&0);
+        (max as f64 / NORMAL as f64).powi(3)
+    }
+
+    fn set_mock_linear(&mut self, v: f64) {
+        let v = (v.cbrt() * NORMAL as f64) as u64;
+        let max = *self.levels.iter().max().unwrap();
+
+        if max > 0 {
+            self.levels.iter_mut()
+                .for_each(|i| *i = (*i as u64 * v / max as u64).min(u32::MAX as u64 / 2) as u32);
+        }
+        else { self.levels.fill(v as u32); }
+    }
+}
+
+fn volume(levels: &[u32]) -> Volume {
+    Volume {
+        levels: VolumeLevels(SmallVec::from_slice(levels)),
+        amplitude: &Volume::mock_linear,
+        set_amplitude: &Volume::set_mock_linear,
+    }
+}
+
+pub fn software_client(id: u32) -> OutputClient {
+    OutputClient {
+        id,
+        process: None,
+        binary: None,
+        app_id: None,
+        entry: None,
+        name: String::new(),
+        description: String::new(),
+        icon: None,
+        volume: volume(&[NORMAL, NORMAL]),
+        max_volume: crate::curve::config().curve.position((u32::MAX / 2 / NORMAL) as f64),
+        muted: false,
+        corked: false,
+        kind: Kind::Out | Kind::Software,
+    }
+}
+
+fn master_client() -> OutputClient {
+    OutputClient {
+        name: "Master".to_owned(),
+        kind: Kind::Out | Kind::Hardware,
+        ..software_client(0)
+    }
+}
+
+fn apply_properties(client: &mut OutputClient, properties: &[Property]) {
+    for property in properties.iter().cloned() {
+        match property {
+            Property::Name(name) => client.name = name,
+            Property::Description(description) => client.description = description,
+            Property::Icon(icon) => client.icon = Some(icon),
+            Property::Process(process) => client.process = Some(process),
+            Property::Binary(binary) => client.binary = Some(binary),
+            Property::Flatpak(app_id) => client.app_id = Some(app_id),
+            Property::Entry(entry) => client.entry = Some(entry),
+            Property::Volume(levels) => client.volume = volume(&levels),
+            Property::Muted(flag) => client.muted = flag,
+            Property::Corked(flag) => client.corked = flag,
+        }
+    }
+}
+
+/// Splits a line by whitespace, keeping "quoted text" together.
+fn tokenize(line: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    let mut started = false;
+
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            },
+            c if c.is_whitespace() && !quoted => {
+                if started {
+                    tokens.push(std::mem::take(&mut token));
+                    started = false;
+                }
+            },
+            c => {
+                token.push(c);
+                started = true;
+            },
+        }
+    }
+
+    if quoted {
+        return Err("unclosed quote".to_owned())
+    }
+
+    if started {
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+fn parse_event(line: &str) -> Result<Event, String> {
+    let tokens = tokenize(line)?;
+    let mut tokens = tokens.iter().map(String::as_str);
+
+    let at = tokens.next()
+        .and_then(|ms| ms.parse::<u64>().ok())
+        .map(Duration::from_millis)
+        .ok_or("expected time in milliseconds")?;
+
+    let action = tokens.next().ok_or("expected an action")?;
+
+    let mut id = || tokens.next()
+        .and_then(|id| id.parse::<u32>().ok())
+        .ok_or(format!("'{action}' expects a client id"));
+
+    let action = match action {
+        "new"        => Action::New(id()?, parse_properties(tokens)?),
+        "change"     => Action::Change(id()?, parse_properties(tokens)?),
+        "remove"     => Action::Remove(id()?),
+        "peak"       => {
+            let id = id()?;
+            let peaks = tokens
+                .map(|peak| peak.parse::<f32>().map_err(|_| format!("'{peak}' is not a valid peak")))
+                .collect::<Result<_, _>>()?;
+
+            Action::Peak(id, peaks)
+        },
+        "master"     => Action::Master(parse_properties(tokens)?),
+        "output"     => {
+            let name = tokens.next().ok_or("'output' expects a name")?.to_owned();
+
+            let mut port = String::new();
+            let mut description = name.clone();
+            let mut default = false;
+
+            for token in tokens {
+                match token.split_once('=') {
+                    Some(("port", value)) => port = value.to_owned(),
+                    Some(("description", value)) => description = value.to_owned(),
+                    None if token == "default" => default = true,
+                    _ => return Err(format!("'{token}' is not a valid output argument")),
+                }
+            }
+
+            Action::Output { output: Output { name, port, master: false }, description, default }
+        },
+        "unplug"     => Action::Unplug(tokens.next().ok_or("'unplug' expects a name")?.to_owned()),
+        "disconnect" => Action::Disconnect,
+        _            => return Err(format!("'{action}' is not a valid action")),
+    };
+
+    Ok(Event { at, action })
+}
+
+fn parse_properties<'a>(tokens: impl Iterator<Item = &'a str>) -> Result<Vec<Property>, String> {
+    let flag = |value: Option<&str>| match value {
+        None | Some("true") => Ok(true),
+        Some("false")       => Ok(false),
+        Some(value)         => Err(format!("'{value}' is not a valid flag")),
+    };
+
+    tokens.map(|token| {
+        let (key, value) = match token.split_once('=') {
+            Some((key, value)) => (key, Some(value)),
+            None => (token, None),
+        };
+
+        let value_of = || value.map(str::to_owned).ok_or(format!("'{key}' expects a value"));
+
+        let property = match key {
+            "name"        => Property::Name(value_of()?),
+            "description" => Property::Description(value_of()?),
+            "icon"        => Property::Icon(value_of()?),
+            "binary"      => Property::Binary(value_of()?),
+            "flatpak"     => Property::Flatpak(value_of()?),
+            "entry"       => Property::Entry(value_of()?),
+            "process"     => Property::Process(value_of()?.parse().map_err(|_| "'process' expects a number")?),
+            "volume"      => Property::Volume(value_of()?
+                .split(',')
+                .map(|percent| percent.parse::<f64>()
+                    .map(|percent| (percent / 100.0 * NORMAL as f64) as u32)
+                    .map_err(|_| format!("'{percent}' is not a valid volume")))
+                .collect::<Result<_, _>>()?),
+            "muted"       => Property::Muted(flag(value)?),
+            "corked"      => Property::Corked(flag(value)?),
+            _             => return Err(format!("'{key}' is not a valid property")),
+        };
+
+        Ok(property)
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::app::CommandMessage;
+
+    const SCRIPT: &str = r#"
+        # Speakers with a single client
+        0   output  speakers port=analog default
+        0   output  hdmi
+        0   master  description=Speakers volume=70
+        0   new     1 name=Firefox description="Playing Video" volume=50,60
+        20  change  1 muted
+        40  peak    1 0.5 0.7
+        60  change  1 corked
+        80  remove  1
+        90  unplug  hdmi
+        100 disconnect
+    "#;
+
+    const PLAN: Kind = Kind::Software.union(Kind::Hardware).union(Kind::Out);
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        glib::MainContext::new().block_on(future)
+    }
+
+    /// Connects to the mock on a separate thread, like the mixer does.
+    fn connect(mock: &Arc<Mock>) -> (relm4::Sender<CommandMessage>, relm4::Receiver<CommandMessage>) {
+        let (sender, receiver) = relm4::channel::<CommandMessage>();
+
+        std::thread::spawn({
+            let mock = mock.clone();
+            let sender = sender.clone();
+
+            move || mock.connect(&sender)
+        });
+
+        assert!(matches!(next(&receiver), Message::Ready));
+
+        (sender, receiver)
+    }
+
+    fn next(receiver: &relm4::Receiver<CommandMessage>) -> Message {
+        match receiver.recv_sync() {
+            Some(CommandMessage::Server(message)) => message,
+            _ => panic!("expected a message from the server"),
+        }
+    }
+
+    #[test]
+    fn parse_script() {
+        let mock = Mock::parse(SCRIPT).unwrap();
+
+        assert_eq!(mock.timeline.len(), 10);
+        assert!(matches!(mock.timeline[3].action, Action::New(1, ref properties) if properties.len() == 3));
+        assert!(matches!(mock.timeline[8].action, Action::Unplug(ref name) if name == "hdmi"));
+        assert!(matches!(mock.timeline[9].action, Action::Disconnect));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let e = Mock::parse("0 new 1\nsoon remove 1").err().unwrap();
+        assert!(matches!(e, MockError::Parse { line: 2, .. }));
+
+        assert!(Mock::parse("0 dance 1").is_err());
+        assert!(Mock::parse("0 new 1 volume=loud").is_err());
+        assert!(Mock::parse("0 new 1 name=\"Unclosed").is_err());
+        assert!(Mock::parse("0 change muted").is_err());
+    }
+
+    #[test]
+    fn quoted_values() {
+        let tokens = tokenize(r#"0 new 1 description="Playing  Video" name=Firefox"#).unwrap();
+        assert_eq!(tokens, ["0", "new", "1", "description=Playing  Video", "name=Firefox"]);
+    }
+
+    #[test]
+    fn timeline() {
+        let mock = Arc::new(Mock::parse(SCRIPT).unwrap());
+        let (sender, receiver) = connect(&mock);
+
+        block_on(async {
+            mock.request_outputs(&sender).await.unwrap();
+            mock.request_master(&sender).await.unwrap();
+            mock.request_software(&sender).await.unwrap();
+            mock.set_peaking(true, &sender).await.unwrap();
+            mock.subscribe(PLAN, &sender).await.unwrap();
+        });
+
+        assert!(matches!(next(&receiver), Message::Output(MessageOutput::New(ref output)) if output.name == "speakers"));
+        assert!(matches!(next(&receiver), Message::Output(MessageOutput::New(ref output)) if output.name == "hdmi"));
+
+        // Sinks come once for all of their ports
+        assert!(matches!(next(&receiver), Message::Output(MessageOutput::Sink(ref sink)) if sink.id == 0 && sink.name == "speakers"));
+        assert!(matches!(next(&receiver), Message::Output(MessageOutput::Sink(ref sink)) if sink.id == 1 && sink.name == "hdmi"));
+
+        assert!(matches!(next(&receiver), Message::OutputClient(MessageClient::New(ref client)) if client.id == 0));
+        assert!(matches!(next(&receiver), Message::Output(MessageOutput::Master(ref output)) if output.port == "analog"));
+
+        let Message::OutputClient(MessageClient::New(client)) = next(&receiver) else {
+            panic!("expected a new client");
+        };
+
+        assert_eq!(client.name, "Firefox");
+        assert_eq!(client.description, "Playing Video");
+        assert_eq!(client.volume.levels.as_slice(), [NORMAL / 2, NORMAL * 6 / 10]);
+
+        assert!(matches!(next(&receiver), Message::OutputClient(MessageClient::Changed(ref client)) if client.muted));
+        assert!(matches!(next(&receiver), Message::OutputClient(MessageClient::Peak(1, ref peaks)) if peaks.len() == 2));
+        assert!(matches!(next(&receiver), Message::OutputClient(MessageClient::Changed(ref client)) if client.corked));
+        assert!(matches!(next(&receiver), Message::OutputClient(MessageClient::Removed(1))));
+        assert!(matches!(next(&receiver), Message::Output(MessageOutput::SinkRemoved(1))));
+        assert!(matches!(next(&receiver), Message::Disconnected(None)));
+    }
+
+    #[test]
+    fn peaks_by_default() {
+        let mock = Arc::new(Mock::parse("0 new 1\n10 peak 1 0.5").unwrap());
+        let (sender, receiver) = connect(&mock);
+
+        block_on(mock.subscribe(PLAN, &sender)).unwrap();
+
+        assert!(matches!(next(&receiver), Message::OutputClient(MessageClient::Peak(1, ref peaks)) if peaks.len() == 1));
+
+        mock.disconnect();
+    }
+
+    #[test]
+    fn records_calls() {
+        let mock = Arc::new(Mock::parse("0 new 7 volume=100").unwrap());
+        let (sender, _receiver) = connect(&mock);
+
+        block_on(async {
+            mock.subscribe(PLAN, &sender).await.unwrap();
+
+            mock.set_volume([7], Kind::Software | Kind::Out, VolumeLevels(smallvec::smallvec![NORMAL / 4])).await.unwrap();
+
+            // Unknown ids and outputs are reported, the rest is still applied
+            let e = mock.set_mute([7, 8], Kind::Software | Kind::Out, true).await.err().unwrap();
+            assert!(matches!(e, Error::Mock(MockError::UnknownClient(8))));
+            assert_eq!(e.client(), Some(8));
+
+            let e = mock.set_output_by_name("headphones", Some("analog")).await.err().unwrap();
+            assert!(matches!(e, Error::Mock(MockError::UnknownOutput(ref name)) if name == "headphones"));
+
+            let e = mock.move_clients([7], "headphones").await.err().unwrap();
+            assert!(matches!(e, Error::Mock(MockError::UnknownOutput(_))));
+        });
+
+        let calls = mock.calls();
+        assert_eq!(calls.len(), 4);
+
+        assert!(matches!(calls[0], Call::Volume { ref ids, ref levels, .. } if ids == &[7] && levels[0] == NORMAL / 4));
+        assert!(matches!(calls[1], Call::Mute { ref ids, flag: true, .. } if ids == &[7, 8]));
+        assert!(matches!(calls[2], Call::Output { ref name, ref port } if name == "headphones" && port.as_deref() == Some("analog")));
+        assert!(matches!(calls[3], Call::Move { ref ids, ref output } if ids == &[7] && output == "headphones"));
+
+        mock.disconnect();
+    }
+
+    #[test]
+    fn echoes_changes() {
+        let mock = Arc::new(Mock::parse("0 new 3 volume=100").unwrap());
+        let (sender, receiver) = connect(&mock);
+
+        block_on(async {
+            mock.subscribe(PLAN, &sender).await.unwrap();
+            mock.set_volume([3], Kind::Software | Kind::Out, VolumeLevels(smallvec::smallvec![NORMAL / 2, NORMAL / 4])).await.unwrap();
+        });
+
+        let Message::OutputClient(MessageClient::Changed(client)) = next(&receiver) else {
+            panic!("expected a changed client");
+        };
+
+        assert_eq!(client.volume.levels.as_slice(), [NORMAL / 2, NORMAL / 4]);
+        assert!((client.volume.amplitude() - 0.125).abs() < 0.0001);
+
+        mock.disconnect();
+
+        assert!(matches!(next(&receiver), Message::Disconnected(None)));
+    }
+}
@@ -1,5 +1,7 @@
 #[cfg(feature = "PipeWire")]
 pub mod pipewire;
+#[cfg(any(feature = "Mock", test))]
+pub mod mock;
 pub mod pulse;
 pub mod error;
 
@@ -11,6 +13,8 @@ use error::Error;
 
 #[cfg(feature = "PipeWire")]
 use self::pipewire::Pipewire;
+#[cfg(any(feature = "Mock", test))]
+use self::mock::Mock;
 use self::pulse::Pulse;
 
 pub struct InnerSender<T, U> {
@@ -44,17 +48,39 @@ impl<T, U> From<&relm4::Sender<T>> for InnerSender<T, U> {
 pub type Sender<T> = InnerSender<crate::app::CommandMessage, T>;
 
 #[derive(Debug, Clone, Deref, DerefMut)]
-pub struct VolumeLevels(smallvec::SmallVec<[u32; 2]>);
+pub struct VolumeLevels(pub(crate) smallvec::SmallVec<[u32; 2]>);
+
+#[derive(Debug, Clone, Deref, DerefMut)]
+pub struct Peaks(smallvec::SmallVec<[f32; 2]>);
+
+pub const DEFAULT_PEAK_RATE: u32 = 30;
+
+#[derive(Debug, Clone, Copy)]
+pub struct PeakConfig {
+    /// Probe every channel separately instead of a downmixed mono
+    pub per_channel: bool,
+    /// Peaks per second, 0 disables peak detection
+    pub rate: u32,
+    /// Don't probe clients that are paused
+    pub pause_corked: bool,
+}
+
+impl Default for PeakConfig {
+    fn default() -> Self {
+        Self { per_channel: false, rate: DEFAULT_PEAK_RATE, pause_corked: false }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Volume {
     pub levels: VolumeLevels,
 
+    /// Linear amplitude of the loudest channel, 1.0 is the normal volume
     #[debug(skip)]
-    percent: &'static (dyn Fn(&Self) -> f64 + Sync),
+    amplitude: &'static (dyn Fn(&Self) -> f64 + Sync),
 
     #[debug(skip)]
-    set_percent: &'static (dyn Fn(&mut Self, f64) + Sync),
+    set_amplitude: &'static (dyn Fn(&mut Self, f64) + Sync),
 }
 
 impl PartialEq for Volume {
@@ -64,12 +90,19 @@ impl PartialEq for Volume {
 }
 
 impl Volume {
+    #[inline]
+    pub fn amplitude(&self) -> f64 {
+        (self.amplitude)(self)
+    }
+
+    /// Position on a slider according to the volume curve.
     pub fn percent(&self) -> f64 {
-        (self.percent)(self)
+        crate::curve::config().curve.position(self.amplitude())
     }
 
     pub fn set_percent(&mut self, p: f64) {
-        (self.set_percent)(self, p)
+        let amplitude = crate::curve::config().curve.amplitude(p);
+        (self.set_amplitude)(self, amplitude)
     }
 }
 
@@ -77,6 +110,11 @@ impl Volume {
 pub struct OutputClient {
     pub id: u32,
     pub process: Option<u32>,
+    pub binary: Option<String>,
+    /// Flatpak application ID
+    pub app_id: Option<String>,
+    /// Application ID, usually the name of its desktop entry
+    pub entry: Option<String>,
     pub name: String,
     pub description: String,
     pub icon: Option<String>,
@@ -94,6 +132,14 @@ pub struct Output {
     pub master: bool,
 }
 
+/// Output that clients can be moved to, with or without ports.
+#[derive(Debug, Clone)]
+pub struct Sink {
+    pub id: u32,
+    pub name: String,
+    pub description: String,
+}
+
 #[derive(Debug)]
 pub enum Message {
     Output(MessageOutput),
@@ -108,7 +154,7 @@ pub enum MessageClient {
     New(Box<OutputClient>),
     Changed(Box<OutputClient>),
     Removed(u32),
-    Peak(u32, f32),
+    Peak(u32, Peaks),
 }
 
 impl From<MessageClient> for Message {
@@ -121,6 +167,8 @@ impl From<MessageClient> for Message {
 pub enum MessageOutput {
     New(Output),
     Master(Output),
+    Sink(Sink),
+    SinkRemoved(u32),
 }
 
 impl From<MessageOutput> for Message {
@@ -134,6 +182,8 @@ pub enum AudioServerEnum {
     Pulse,
     #[cfg(feature = "PipeWire")]
     Pipewire,
+    #[cfg(any(feature = "Mock", test))]
+    Mock,
 }
 
 bitflags::bitflags! {
@@ -154,7 +204,9 @@ pub trait AudioServer {
     async fn request_master(&self, sender: impl Into<Sender<Message>>) -> Result<(), Error>;
     async fn request_outputs(&self, sender: impl Into<Sender<Message>>) -> Result<(), Error>;
     async fn subscribe(&self, plan: Kind, sender: impl Into<Sender<Message>>) -> Result<(), Error>;
-    async fn set_volume(&self, ids: impl IntoIterator<Item = u32>, kind: Kind, levels: VolumeLevels);
-    async fn set_mute(&self, ids: impl IntoIterator<Item = u32>, kind: Kind, flag: bool);
-    async fn set_output_by_name(&self, name: &str, port: Option<&str>);
+    async fn set_peaking(&self, flag: bool, sender: impl Into<Sender<Message>>) -> Result<(), Error>;
+    async fn set_volume(&self, ids: impl IntoIterator<Item = u32>, kind: Kind, levels: VolumeLevels) -> Result<(), Error>;
+    async fn set_mute(&self, ids: impl IntoIterator<Item = u32>, kind: Kind, flag: bool) -> Result<(), Error>;
+    async fn set_output_by_name(&self, name: &str, port: Option<&str>) -> Result<(), Error>;
+    async fn move_clients(&self, ids: impl IntoIterator<Item = u32>, output: &str) -> Result<(), Error>;
 }
@@ -1,50 +1,150 @@
 use std::borrow::Cow;
 use std::cell::RefCell;
 use std::pin::Pin;
-use std::sync::{Arc, atomic::{AtomicU8, Ordering}, OnceLock, Weak};
-use std::thread::Thread;
+use std::sync::{Arc, atomic::{AtomicU8, Ordering}, mpsc};
 
 use libpulse_binding::callbacks::ListResult;
-use libpulse_binding::context::{self, introspect::{Introspector, SinkInfo, SinkInputInfo}, subscribe::{Facility, InterestMaskSet, Operation}, Context, State};
+use libpulse_binding::channelmap::Map;
+use libpulse_binding::context::{self, introspect::{ServerInfo, SinkInfo, SinkInputInfo}, subscribe::{Facility, InterestMaskSet, Operation}, Context, State};
 use libpulse_binding::def::{BufferAttr, PortAvailable, Retval};
-use libpulse_binding::mainloop::standard::Mainloop;
+use libpulse_binding::error::Code;
+use libpulse_binding::mainloop::{api::MainloopInnerType, standard::Mainloop};
 use libpulse_binding::proplist::{properties::APPLICATION_NAME, Proplist};
 use libpulse_binding::sample::{Format, Spec};
 use libpulse_binding::stream::{Stream, self, PeekResult};
 use libpulse_binding::volume::ChannelVolumes;
 
-use derive_more::derive::{Deref, From};
-use parking_lot::{Mutex, MutexGuard};
+use parking_lot::Mutex;
 use smallvec::SmallVec;
 
-use tokio::sync::watch;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
+use tokio_util::sync::CancellationToken;
 
 use super::error::{Error, PulseError};
-use super::{AudioServer, Kind, Message, MessageClient, MessageOutput, Output, OutputClient, Sender, Volume, VolumeLevels};
+use super::{AudioServer, Kind, Message, MessageClient, MessageOutput, Output, OutputClient, PeakConfig, Peaks, Sender, Sink, Volume, VolumeLevels};
 
-const DEFAULT_PEAK_RATE: u32 = 30;
+
+// Index of the master sink before the default sink is known
+const NO_SINK: u32 = u32::MAX;
 
 type Pb<T> = Pin<Box<T>>;
-type Peakers = Vec<Pb<Stream>>;
 
-type WeakContext = Weak<Mutex<RefCell<Context>>>;
-type WeakPeakers = Weak<Mutex<RefCell<Peakers>>>;
+struct Peakers {
+    streams: Vec<Pb<Stream>>,
+    config: PeakConfig,
+    paused: bool,
+}
+
+/// Part of a client that peakers look at, the info itself only lives as long as its callback.
+#[derive(Clone, Copy)]
+struct Probe {
+    index: u32,
+    map: Map,
+    has_volume: bool,
+    corked: bool,
+}
+
+impl From<&SinkInputInfo<'_>> for Probe {
+    fn from(info: &SinkInputInfo) -> Self {
+        Self {
+            index: info.index,
+            map: info.channel_map,
+            has_volume: info.has_volume,
+            corked: info.corked,
+        }
+    }
+}
+
+impl Peakers {
+    fn wants(&self, probe: &Probe) -> bool {
+        probe.has_volume && self.config.rate != 0 && !self.paused && !(self.config.pause_corked && probe.corked)
+    }
+
+    fn contains(&self, i: u32) -> bool {
+        self.streams.iter().any(|stream| stream.get_monitor_stream() == Some(i))
+    }
+
+    fn add(&mut self, context: &mut Context, sender: &Sender<Message>, probe: &Probe) {
+        let map = match self.config.per_channel {
+            true  => Some(&probe.map),
+            false => None,
+        };
+
+        if let Some(p) = create_peeker(context, sender, probe.index, map, self.config.rate) {
+            self.streams.push(p)
+        }
+    }
+
+    fn remove(&mut self, i: u32) {
+        if let Some(pos) = self.streams.iter().position(|stream| stream.get_monitor_stream() == Some(i)) {
+            self.streams.remove(pos);
+        }
+    }
+}
+
+/// Everything that belongs to a connection, owned by the mainloop thread.
+struct Session {
+    context: Context,
+    peakers: Peakers,
+    /// Index of the default sink that the master slider controls
+    master:  u32,
+    queue:   Queue,
+    /// Cancelled when the connection is gone, operations in flight never call back after that
+    closed:  CancellationToken,
+}
+
+impl Session {
+    // Creates or destroys a peaker depending on the client state and settings
+    fn sync_peaker(&mut self, sender: &Sender<Message>, probe: &Probe) {
+        if let State::Ready = self.context.get_state() {
+            match (self.peakers.wants(probe), self.peakers.contains(probe.index)) {
+                (true, false) => self.peakers.add(&mut self.context, sender, probe),
+                (false, true) => self.peakers.remove(probe.index),
+                _ => {},
+            }
+        }
+    }
+}
+
+type Command = Box<dyn FnOnce(&mut Session) + Send>;
+
+/// Commands for the mainloop thread.
+// Context can't be reached from its own callbacks, so they queue whatever needs it next.
+// Mainloop runs the queue right after the dispatch, without being woken up.
+#[derive(Clone)]
+struct Queue(mpsc::Sender<Command>);
+
+impl Queue {
+    fn push(&self, command: impl FnOnce(&mut Session) + Send + 'static) -> Result<(), PulseError> {
+        self.0.send(Box::new(command)).map_err(|_| PulseError::NotConnected)
+    }
+}
 
 pub struct Pulse {
-    context: Arc<Mutex<RefCell<Context>>>,
-    peakers: Arc<Mutex<RefCell<Peakers>>>,
-    state:   Arc<AtomicU8>,
-    lock:    watch::Sender<Lock>,
-    thread:  Mutex<Thread>,
-    running: Mutex<()>,
+    peak:     PeakConfig,
+    state:    Arc<AtomicU8>,
+    commands: Mutex<Option<Commands>>,
+    running:  Mutex<()>,
 }
 
-#[repr(u8)]
-#[derive(PartialEq)]
-enum Lock {
-    Unlocked = 0,
-    Locked   = 1,
-    Aquire   = 2,
+/// Way into the mainloop thread, exists only while the mainloop is running.
+struct Commands {
+    queue:  Queue,
+    waker:  Waker,
+}
+
+struct Waker(*mut libpulse_sys::pa_mainloop);
+
+// pa_mainloop_wakeup only writes into the wakeup pipe of the mainloop, which is safe to do from any thread.
+// Pointer is cleared before the mainloop is destroyed.
+unsafe impl Send for Waker {}
+
+impl Waker {
+    #[inline]
+    fn wake(&self) {
+        unsafe { libpulse_sys::pa_mainloop_wakeup(self.0) }
+    }
 }
 
 impl Pulse {
@@ -52,18 +152,12 @@ impl Pulse {
         static MAINLOOP: RefCell<Mainloop> = RefCell::new(Mainloop::new().unwrap());
     }
 
-    pub fn new() -> Self {
-        let context = Pulse::MAINLOOP.with_borrow(|mainloop| {
-            Context::new(mainloop, "Mixxc Context").unwrap()
-        });
-
+    pub fn new(peak: PeakConfig) -> Self {
         Self {
-            context: Arc::new(Mutex::new(RefCell::new(context))),
-            peakers: Arc::new(Mutex::new(RefCell::new(Vec::with_capacity(8)))),
-            state:   Arc::new(AtomicU8::new(0)),
-            lock:    watch::channel(Lock::Unlocked).0,
-            thread:  Mutex::new(std::thread::current()),
-            running:      Mutex::new(()),
+            peak,
+            state:    Arc::new(AtomicU8::new(0)),
+            commands: Mutex::new(None),
+            running:  Mutex::new(()),
         }
     }
 
@@ -77,59 +171,37 @@ impl Pulse {
         self.state.load(Ordering::Acquire) == State::Ready as u8
     }
 
-    #[inline]
-    fn is_terminated(&self) -> bool {
-        self.state.load(Ordering::Acquire) == State::Terminated as u8
-    }
+    /// Queues a command for the mainloop thread, which owns the context.
+    fn execute(&self, command: impl FnOnce(&mut Session) + Send + 'static) -> Result<(), PulseError> {
+        let commands = self.commands.lock();
+        let commands = commands.as_ref().ok_or(PulseError::NotConnected)?;
 
-    async fn lock(&self) -> ContextRef<'_> {
-        self.lock.send_replace(Lock::Aquire);
-        self.lock.subscribe().wait_for(|lock| *lock == Lock::Locked).await.unwrap();
+        commands.queue.push(command)?;
+        commands.waker.wake();
 
-        let context = self.context.try_lock().unwrap();
-        let thread = self.thread.try_lock().unwrap();
-
-        ContextRef {
-            context,
-            lock: &self.lock,
-            thread,
-        }
+        Ok(())
     }
 
-    fn lock_blocking(&self) -> ContextRef<'_> {
-        self.lock.send_replace(Lock::Aquire);
-        while *self.lock.borrow() != Lock::Locked { std::hint::spin_loop(); }
+    /// Runs a command on the mainloop thread and waits until it's done.
+    async fn run<T: Send + 'static>(&self, command: impl FnOnce(&mut Session) -> T + Send + 'static) -> Result<T, PulseError> {
+        let (sender, receiver) = oneshot::channel();
 
-        let context = self.context.try_lock().unwrap();
-        let thread = self.thread.try_lock().unwrap();
+        self.execute(move |session| {
+            let _ = sender.send(command(session));
+        })?;
 
-        ContextRef {
-            context,
-            lock: &self.lock,
-            thread,
-        }
+        receiver.await.map_err(|_| PulseError::NotConnected)
     }
 
-    fn iterate(timeout: &Duration) -> Result<u32, PulseError> {
+    // Blocks until there is something to dispatch or until the mainloop is woken up
+    fn iterate() -> Result<u32, PulseError> {
         Self::MAINLOOP.with_borrow_mut(|mainloop| {
-            mainloop.prepare(timeout.into()).map_err(PulseError::from)?;
+            mainloop.prepare(None).map_err(PulseError::from)?;
             mainloop.poll().map_err(PulseError::from)?;
             mainloop.dispatch().map_err(PulseError::from)
         })
     }
 
-    fn is_locked(&self) -> bool {
-        self.lock.send_if_modified(|lock| {
-            match *lock == Lock::Aquire {
-                true => {
-                    *lock = Lock::Locked;
-                    true
-                }
-                false => false,
-            }
-        })
-    }
-
     fn quit() {
         Self::MAINLOOP.with_borrow_mut(|mainloop| mainloop.quit(Retval(0)));
     }
@@ -144,71 +216,83 @@ impl AudioServer for Pulse {
         let mut proplist = Proplist::new().unwrap();
         proplist.set_str(APPLICATION_NAME, crate::APP_NAME).unwrap();
 
-        self.context.lock().replace(Pulse::MAINLOOP.with_borrow(|mainloop| {
+        let mut context = Pulse::MAINLOOP.with_borrow(|mainloop| {
             Context::new_with_proplist(mainloop, "Mixxc Context", &proplist).unwrap()
-        }));
+        });
 
         let sender: Sender<Message> = sender.into();
 
+        let (queue, receiver) = mpsc::channel();
+        let queue = Queue(queue);
+
         let state_callback = Box::new({
-            let context = Arc::downgrade(&self.context);
-            let state = Arc::downgrade(&self.state);
+            let queue = queue.clone();
+            let state = self.state.clone();
             let sender = sender.clone();
 
-            move || state_callback(&context, &state, &sender)
+            move || {
+                let state = state.clone();
+                let sender = sender.clone();
+
+                let _ = queue.push(move |session| state_callback(session, &state, &sender));
+            }
         });
 
-        {
-            let guard = self.context.lock();
-            let mut context = guard.borrow_mut();
+        // Manually calls state_callback and sets state to Connecting on success
+        context.connect(None, context::FlagSet::NOAUTOSPAWN, None)
+            .map_err(PulseError::from)?;
 
-            // Manually calls state_callback and sets state to Connecting on success
-            context.connect(None, context::FlagSet::NOAUTOSPAWN, None)
-                .map_err(PulseError::from)?;
+        self.set_state(State::Connecting);
 
-            self.set_state(State::Connecting);
+        context.set_state_callback(Some(state_callback));
 
-            context.set_state_callback(Some(state_callback));
-        }
+        let _running = self.running.lock();
 
-        *self.thread.lock() = std::thread::current();
+        let mut session = Session {
+            context,
+            peakers: Peakers {
+                streams: Vec::with_capacity(8),
+                config: self.peak,
+                paused: false,
+            },
+            master: NO_SINK,
+            queue: queue.clone(),
+            closed: CancellationToken::new(),
+        };
 
-        let timeout = std::time::Duration::from_millis(1).into();
-        let _running = self.running.lock();
+        let waker = Pulse::MAINLOOP.with_borrow(|mainloop| Waker(mainloop._inner.get_ptr()));
+        self.commands.lock().replace(Commands { queue, waker });
 
         loop {
-            match Pulse::iterate(&timeout) {
+            match Pulse::iterate() {
                 Ok(_) => {},
                 Err(PulseError::MainloopQuit) => break,
                 Err(e) => sender.emit(Message::Error(e.into())),
             };
 
-            if self.is_locked() {
-                std::thread::park();
-
-                if self.is_terminated() {
-                    self.peakers.lock().borrow_mut().clear();
-                    Pulse::quit()
-                }
+            for command in receiver.try_iter() {
+                command(&mut session);
             }
         }
 
+        self.commands.lock().take();
+        session.closed.cancel();
+
         Ok(())
     }
 
     fn disconnect(&self) {
-        {
-            let guard = self.lock_blocking();
-            let mut context = guard.borrow_mut();
-
-            context.set_state_callback(None);
-            context.disconnect();
+        // Context::disconnect calls state_callback, which reports that the connection was terminated
+        let _ = self.execute(|session| {
+            session.context.disconnect();
+            session.peakers.streams.clear();
 
-            // Context::disconnect manually calls state_callback and sets state to Terminated
-            self.set_state(State::Terminated);
-        }
+            Pulse::quit();
+        });
 
         let _running = self.running.lock();
+
+        self.set_state(State::Terminated);
     }
 
     async fn request_software(&self, sender: impl Into<Sender<Message>>) -> Result<(), Error> {
@@ -218,17 +302,13 @@ impl AudioServer for Pulse {
 
         let sender = sender.into();
 
-        let input_callback = {
-            let context = Arc::downgrade(&self.context);
-            let peakers = Arc::downgrade(&self.peakers);
+        self.run(move |session| {
+            let queue = session.queue.clone();
 
-            move |info: ListResult<&SinkInputInfo>| {
-                add_sink_input(info, &context, &sender, &peakers);
-            }
-        };
-
-        let context = self.lock().await;
-        context.introspect().get_sink_input_info_list(input_callback);
+            session.context.introspect().get_sink_input_info_list(move |info| {
+                add_sink_input(info, &queue, &sender);
+            });
+        }).await?;
 
         Ok(())
     }
@@ -245,11 +325,9 @@ impl AudioServer for Pulse {
                 return
             };
 
-            let Some(output_name) = &info.name else {
-                let e = PulseError::NamelessSink(info.index).into();
-                sender.emit(Message::Error(e));
-
-                return;
+            let sink = match Sink::try_from(info) {
+                Ok(sink) => sink,
+                Err(e) => return sender.emit(Message::Error(e.into())),
             };
 
             let ports = info.ports.iter()
@@ -264,7 +342,7 @@ impl AudioServer for Pulse {
                 };
 
                 let output = Output {
-                    name: output_name.to_string(),
+                    name: sink.name.clone(),
                     port: port_name.to_string(),
                     master: false,
                 };
@@ -272,12 +350,14 @@ impl AudioServer for Pulse {
                 let msg: Message = MessageOutput::New(output).into();
                 sender.emit(msg);
             }
-        };
 
-        let guard = self.lock().await;
-        let introspect = guard.introspect();
+            // Clients can be moved to sinks without ports as well
+            sender.emit(Message::from(MessageOutput::Sink(sink)));
+        };
 
-        introspect.get_sink_info_list(sink_info_callback);
+        self.run(move |session| {
+            session.context.introspect().get_sink_info_list(sink_info_callback);
+        }).await?;
 
         Ok(())
     }
@@ -289,37 +369,16 @@ impl AudioServer for Pulse {
 
         let sender = sender.into();
 
-        let sink_callback = move |info: ListResult<&SinkInfo>| {
-            if let ListResult::Item(info) = info {
-                let client: Box<OutputClient> = Box::new(info.into());
-                let msg: Message = MessageClient::New(client).into();
-
-                sender.emit(msg);
-
-                let Some(output_name) = info.name.as_ref() else {
-                    let e = PulseError::NamelessSink(info.index).into();
-                    sender.emit(Message::Error(e));
-
-                    return
-                };
-
-                let Some(port_name) = info.active_port.as_ref().and_then(|p| p.name.as_ref()) else {
-                    return
-                };
-
-                let output = Output {
-                    name: output_name.to_string(),
-                    port: port_name.to_string(),
-                    master: true,
-                };
+        self.run(move |session| {
+            // Master slider is created anew
+            session.master = NO_SINK;
 
-                let msg: Message = MessageOutput::Master(output).into();
-                sender.emit(msg)
-            }
-        };
+            let queue = session.queue.clone();
 
-        let context = self.lock().await;
-        context.introspect().get_sink_info_by_index(0, sink_callback);
+            session.context.introspect().get_server_info(move |info| {
+                request_default_sink(info, &queue, &sender)
+            });
+        }).await?;
 
         Ok(())
     }
@@ -331,19 +390,12 @@ impl AudioServer for Pulse {
 
         let sender = sender.into();
 
-        let subscribe_callback = Box::new({
-            let context = Arc::downgrade(&self.context);
-            let peakers = Arc::downgrade(&self.peakers);
-
-            move |facility, op, i| {
-                subscribe_callback(&sender, &context, &peakers, facility, op, i)
-            }
-        });
-
         let mut mask = InterestMaskSet::NULL;
 
+        // Sinks are where software clients can be moved to
         if plan.contains(Kind::Software) {
             mask |= InterestMaskSet::SINK_INPUT;
+            mask |= InterestMaskSet::SINK;
         }
 
         if plan.contains(Kind::Hardware) {
@@ -351,112 +403,299 @@ impl AudioServer for Pulse {
             mask |= InterestMaskSet::SERVER;
         }
 
-        let guard = self.lock().await;
-        let mut context = guard.borrow_mut();
+        self.run(move |session| {
+            let queue = session.queue.clone();
+
+            session.context.set_subscribe_callback(Some(Box::new(move |facility, op, i| {
+                subscribe_callback(&queue, &sender, facility, op, i)
+            })));
 
-        context.set_subscribe_callback(Some(subscribe_callback));
-        context.subscribe(mask, |_| ());
+            session.context.subscribe(mask, |_| ());
+        }).await?;
 
         Ok(())
     }
 
-    async fn set_volume(&self, ids: impl IntoIterator<Item = u32>, kind: Kind, levels: VolumeLevels) {
+    async fn set_peaking(&self, flag: bool, sender: impl Into<Sender<Message>>) -> Result<(), Error> {
         if !self.is_connected() {
-            return
+            return Err(PulseError::NotConnected.into())
         }
 
-        let context = self.lock().await;
-        let mut introspect = context.introspect();
+        let sender = sender.into();
+
+        self.run(move |session| {
+            let peakers = &mut session.peakers;
+
+            // Already in the requested state
+            if peakers.paused != flag {
+                return
+            }
+
+            peakers.paused = !flag;
+
+            if !flag {
+                peakers.streams.clear();
+                return
+            }
+
+            let queue = session.queue.clone();
+
+            session.context.introspect().get_sink_input_info_list(move |info| {
+                if let ListResult::Item(info) = info {
+                    let sender = sender.clone();
+                    let probe = Probe::from(info);
+
+                    let _ = queue.push(move |session| session.sync_peaker(&sender, &probe));
+                }
+            });
+        }).await?;
+
+        Ok(())
+    }
+
+    async fn set_volume(&self, ids: impl IntoIterator<Item = u32>, kind: Kind, levels: VolumeLevels) -> Result<(), Error> {
+        if !self.is_connected() {
+            return Err(PulseError::NotConnected.into())
+        }
 
+        let ids: SmallVec<[u32; 4]> = ids.into_iter().collect();
         let volume: ChannelVolumes = levels.into();
 
-        for id in ids.into_iter() {
-            match kind {
-                k if k.contains(Kind::Out | Kind::Software) => {
-                    introspect.set_sink_input_volume(id, &volume, None);
-                },
-                k if k.contains(Kind::Out | Kind::Hardware) => {
-                    introspect.set_sink_volume_by_index(id, &volume, None);
-                },
-                _ => {}
-            };
+        let (count, pending) = self.run(move |session| {
+            let (results, pending) = Results::new(session);
+
+            let mut introspect = session.context.introspect();
+            let mut count = 0;
+
+            for id in ids {
+                let callback = Box::new(results.callback(move |code| PulseError::Volume(id, code)));
+
+                match kind {
+                    k if k.contains(Kind::Out | Kind::Software) => {
+                        introspect.set_sink_input_volume(id, &volume, Some(callback));
+                    },
+                    k if k.contains(Kind::Out | Kind::Hardware) => {
+                        introspect.set_sink_volume_by_index(id, &volume, Some(callback));
+                    },
+                    _ => continue,
+                };
+
+                count += 1;
+            }
+
+            (count, pending)
+        }).await?;
+
+        Ok(pending.wait(count).await?)
+    }
+
+    async fn set_mute(&self, ids: impl IntoIterator<Item = u32>, kind: Kind, flag: bool) -> Result<(), Error> {
+        if !self.is_connected() {
+            return Err(PulseError::NotConnected.into())
         }
+
+        let ids: SmallVec<[u32; 4]> = ids.into_iter().collect();
+
+        let (count, pending) = self.run(move |session| {
+            let (results, pending) = Results::new(session);
+
+            let mut introspect = session.context.introspect();
+            let mut count = 0;
+
+            for id in ids {
+                let callback = Box::new(results.callback(move |code| PulseError::Mute(id, code)));
+
+                match kind {
+                    k if k.contains(Kind::Out | Kind::Software) => {
+                        introspect.set_sink_input_mute(id, flag, Some(callback));
+                    },
+                    k if k.contains(Kind::Out | Kind::Hardware) => {
+                        introspect.set_sink_mute_by_index(id, flag, Some(callback));
+                    },
+                    _ => continue,
+                };
+
+                count += 1;
+            }
+
+            (count, pending)
+        }).await?;
+
+        Ok(pending.wait(count).await?)
     }
 
-    async fn set_mute(&self, ids: impl IntoIterator<Item = u32>, kind: Kind, flag: bool) {
+    async fn set_output_by_name(&self, name: &str, port: Option<&str>) -> Result<(), Error> {
         if !self.is_connected() {
-            return
+            return Err(PulseError::NotConnected.into())
         }
 
-        let context = self.lock().await;
-        let mut introspect = context.introspect();
+        if name.is_empty() {
+            return Err(PulseError::Output(name.to_owned(), Code::NoEntity).into())
+        }
 
-        for id in ids.into_iter() {
-            match kind {
-                k if k.contains(Kind::Out | Kind::Software) => {
-                    introspect.set_sink_input_mute(id, flag, None);
-                },
-                k if k.contains(Kind::Out | Kind::Hardware) => {
-                    introspect.set_sink_mute_by_index(id, flag, None);
-                },
-                _ => {}
+        let name = name.to_owned();
+        let port = port.map(str::to_owned);
+
+        let (count, pending) = self.run(move |session| {
+            let (results, pending) = Results::new(session);
+
+            let error = |name: &str| {
+                let name = name.to_owned();
+                move |code| PulseError::Output(name, code)
             };
+
+            if let Some(port) = &port {
+                let mut introspect = session.context.introspect();
+                let callback = Box::new(results.callback(error(port)));
+
+                introspect.set_sink_port_by_name(&name, port, Some(callback));
+            }
+
+            session.context.set_default_sink(&name, results.callback(error(&name)));
+
+            (1 + port.is_some() as usize, pending)
+        }).await?;
+
+        Ok(pending.wait(count).await?)
+    }
+
+    async fn move_clients(&self, ids: impl IntoIterator<Item = u32>, output: &str) -> Result<(), Error> {
+        if !self.is_connected() {
+            return Err(PulseError::NotConnected.into())
         }
+
+        let ids: SmallVec<[u32; 4]> = ids.into_iter().collect();
+        let output = output.to_owned();
+
+        let (count, pending) = self.run(move |session| {
+            let (results, pending) = Results::new(session);
+
+            let mut introspect = session.context.introspect();
+
+            for &id in &ids {
+                let name = output.clone();
+                let callback = Box::new(results.callback(move |code| PulseError::Move(id, name, code)));
+
+                introspect.move_sink_input_by_name(id, &output, Some(callback));
+            }
+
+            (ids.len(), pending)
+        }).await?;
+
+        Ok(pending.wait(count).await?)
+    }
+}
+
+/// Collects outcomes of the operations started by a single request.
+struct Results {
+    sender: UnboundedSender<Result<(), PulseError>>,
+    queue: Queue,
+}
+
+/// Outcomes of a request that haven't arrived yet.
+struct Pending {
+    receiver: UnboundedReceiver<Result<(), PulseError>>,
+    closed: CancellationToken,
+}
+
+impl Results {
+    fn new(session: &Session) -> (Self, Pending) {
+        let (sender, receiver) = unbounded_channel();
+
+        let results = Self { sender, queue: session.queue.clone() };
+        let pending = Pending { receiver, closed: session.closed.clone() };
+
+        (results, pending)
     }
 
-    async fn set_output_by_name(&self, name: &str, port: Option<&str>) {
-        if !self.is_connected() || name.is_empty() {
-            return
+    /// Success callback of an operation, failure is described with the error code of the context.
+    fn callback(&self, error: impl FnOnce(Code) -> PulseError + Send + 'static) -> impl FnMut(bool) + 'static {
+        let sender = self.sender.clone();
+        let queue = self.queue.clone();
+        let mut error = Some(error);
+
+        move |success| {
+            if success {
+                let _ = sender.send(Ok(()));
+                return
+            }
+
+            let Some(error) = error.take() else { return };
+            let sender = sender.clone();
+
+            // Error code is read right after the dispatch that reported the failure
+            let _ = queue.push(move |session| {
+                let _ = sender.send(Err(error(errno(&session.context))));
+            });
         }
+    }
+}
+
+impl Pending {
+    /// Waits for every operation and returns the first failure.
+    // Operations that are in flight when the connection goes away never call back
+    async fn wait(mut self, count: usize) -> Result<(), PulseError> {
+        let mut outcome = Ok(());
 
-        let context = self.lock().await;
+        for _ in 0..count {
+            let result = tokio::select! {
+                biased;
 
-        if let Some(port) = port {
-            let mut introspect = context.introspect();
+                result = self.receiver.recv() => result,
+                _ = self.closed.cancelled() => None,
+            };
 
-            introspect.set_sink_port_by_name(name, port, None);
+            match result {
+                Some(Err(e)) if outcome.is_ok() => outcome = Err(e),
+                Some(_) => {},
+                None => return Err(PulseError::NotConnected),
+            }
         }
 
-        context.borrow_mut().set_default_sink(name, |_| {});
+        outcome
     }
 }
 
-fn add_sink_input(info: ListResult<&SinkInputInfo>, context: &WeakContext, sender: &Sender<Message>, peakers: &WeakPeakers)
-{
-    let Some(context) = context.upgrade() else { return };
-    let Some(peakers) = peakers.upgrade() else { return };
+fn errno(context: &Context) -> Code {
+    use num_traits::FromPrimitive;
 
+    Code::from_i32(context.errno().0).unwrap_or(Code::Unknown)
+}
+
+fn add_sink_input(info: ListResult<&SinkInputInfo>, queue: &Queue, sender: &Sender<Message>) {
     if let ListResult::Item(info) = info {
         if !info.has_volume { return }
 
         let client: Box<OutputClient> = Box::new(info.into());
-        let id = client.id;
-
         let msg: Message = MessageClient::New(client).into();
         sender.emit(msg);
 
-        let guard = context.lock();
-        let mut context = guard.borrow_mut();
-
-        if let State::Ready = context.get_state() {
-            if let Some(p) = create_peeker(&mut context, sender, id) {
-                let guard = peakers.lock();
-                let mut peakers = guard.borrow_mut();
+        let sender = sender.clone();
+        let probe = Probe::from(info);
 
-                peakers.push(p)
-            }
-        }
+        let _ = queue.push(move |session| session.sync_peaker(&sender, &probe));
     }
 }
 
-fn peak_callback(stream: &mut Stream, sender: &Sender<Message>, i: u32) {
+fn peak_callback(stream: &mut Stream, sender: &Sender<Message>, i: u32, channels: usize) {
+    const SAMPLE: usize = std::mem::size_of::<f32>();
+
     match stream.peek() {
         Ok(PeekResult::Data(b)) => {
-            let bytes: [u8; 4] = unsafe { b.try_into().unwrap_unchecked() };
-            let peak: f32 = f32::from_ne_bytes(bytes);
-            let msg: Message = MessageClient::Peak(i, peak).into();
+            let mut peaks = Peaks(SmallVec::from_elem(0.0, channels));
+
+            // Fragment might contain more than a single frame
+            for frame in b.chunks_exact(SAMPLE * channels) {
+                for (peak, sample) in peaks.iter_mut().zip(frame.chunks_exact(SAMPLE)) {
+                    let sample = f32::from_ne_bytes(sample.try_into().unwrap());
+                    *peak = peak.max(sample);
+                }
+            }
 
-            if peak != 0.0 { sender.emit(msg); }
+            if peaks.iter().any(|peak| *peak != 0.0) {
+                let msg: Message = MessageClient::Peak(i, peaks).into();
+                sender.emit(msg);
+            }
         }
         Ok(PeekResult::Hole(_)) => {},
         _ => return,
@@ -465,36 +704,35 @@ fn peak_callback(stream: &mut Stream, sender: &Sender<Message>, i: u32) {
     let _ = stream.discard();
 }
 
-fn create_peeker(context: &mut Context, sender: &Sender<Message>, i: u32) -> Option<Pb<Stream>> {
+// Map is only provided if every channel should be probed separately
+fn create_peeker(context: &mut Context, sender: &Sender<Message>, i: u32, map: Option<&Map>, rate: u32) -> Option<Pb<Stream>> {
     use stream::FlagSet;
 
-    const PEAK_BUF_ATTR: &BufferAttr = &BufferAttr {
-        maxlength: std::mem::size_of::<f32>() as u32,
-        fragsize:  std::mem::size_of::<f32>() as u32,
+    let map = map.filter(|map| map.is_valid());
 
-        prebuf: 0, minreq: 0, tlength: 0,
+    let spec = Spec {
+        channels: map.map(Map::len).unwrap_or(1),
+        format:   Format::FLOAT32NE,
+        rate,
     };
 
-    static PEAK_SPEC: OnceLock<Spec> = OnceLock::new();
+    let frame = (std::mem::size_of::<f32>() * spec.channels as usize) as u32;
 
-    let spec = PEAK_SPEC.get_or_init(|| {
-        Spec {
-            channels: 1,
-            format:   Format::FLOAT32NE,
-            rate: std::env::var("PULSE_PEAK_RATE").ok()
-                .and_then(|s| s.parse::<u32>().ok())
-                .unwrap_or(DEFAULT_PEAK_RATE)
-        }
-    });
+    let buffer_attr = BufferAttr {
+        maxlength: frame,
+        fragsize:  frame,
+
+        prebuf: 0, minreq: 0, tlength: 0,
+    };
 
     const FLAGS: FlagSet = FlagSet::PEAK_DETECT
             .union(FlagSet::DONT_INHIBIT_AUTO_SUSPEND)
             .union(FlagSet::PASSTHROUGH)
             .union(FlagSet::START_UNMUTED);
 
-    let mut stream = Stream::new(context, "Mixxc Peaker", spec, None)?;
+    let mut stream = Stream::new(context, "Mixxc Peaker", &spec, map)?;
     stream.set_monitor_stream(i).ok()?;
-    stream.connect_record(None, Some(PEAK_BUF_ATTR), FLAGS).ok()?;
+    stream.connect_record(None, Some(&buffer_attr), FLAGS).ok()?;
 
     let mut stream = Box::pin(stream);
 
@@ -502,7 +740,9 @@ fn create_peeker(context: &mut Context, sender: &Sender<Message>, i: u32) -> Opt
         let sender = sender.clone();
         let stream: &mut Stream = unsafe { &mut *(stream.as_mut().get_mut() as *mut Stream) };
 
-        move |_| peak_callback(stream, &sender, i)
+        let channels = spec.channels as usize;
+
+        move |_| peak_callback(stream, &sender, i, channels)
     });
 
     stream.set_read_callback(Some(peak_callback));
@@ -510,184 +750,160 @@ fn create_peeker(context: &mut Context, sender: &Sender<Message>, i: u32) -> Opt
     Some(stream)
 }
 
-fn handle_server_change(sender: &Sender<Message>, context: &WeakContext) {
-    let Some(introspect) = try_introspect(context) else { return };
 
-    let context = context.clone();
+fn handle_server_change(session: &mut Session, sender: &Sender<Message>) {
+    let queue = session.queue.clone();
     let sender = sender.clone();
 
-    introspect.get_server_info(move |info| {
-        let Some(introspect) = try_introspect(&context) else { return };
-        let Some(name) = &info.default_sink_name else { return };
+    session.context.introspect().get_server_info(move |info| request_default_sink(info, &queue, &sender));
+}
 
-        let sender = sender.clone();
+fn handle_sink_change(session: &mut Session, sender: &Sender<Message>, op: Operation, i: u32) {
+    let sender = sender.clone();
 
-        let output_name = name.to_string();
+    match op {
+        Operation::New => {
+            session.context.introspect().get_sink_info_by_index(i, move |info| if let ListResult::Item(info) = info {
+                match Sink::try_from(info) {
+                    Ok(sink) => sender.emit(Message::from(MessageOutput::Sink(sink))),
+                    Err(e) => sender.emit(Message::Error(e.into())),
+                }
+            });
+        },
+        // Removal of the default sink is followed by a server change with the new default
+        Operation::Removed => sender.emit(Message::from(MessageOutput::SinkRemoved(i))),
+        Operation::Changed if session.master == i => {
+            let queue = session.queue.clone();
 
-        introspect.get_sink_info_by_name(name, move |info| {
-            let ListResult::Item(info) = info else {
-                return
-            };
+            session.context.introspect().get_sink_info_by_index(i, move |info| if let ListResult::Item(info) = info {
+                emit_master(info, &queue, &sender);
+            });
+        },
+        Operation::Changed => {},
+    }
+}
 
-            let Some(port_name) = info.active_port.as_ref().and_then(|p| p.name.as_ref()) else {
-                return
-            };
+fn request_default_sink(info: &ServerInfo, queue: &Queue, sender: &Sender<Message>) {
+    let Some(name) = info.default_sink_name.as_ref().map(|name| name.to_string()) else { return };
 
-            let output = Output {
-                name: output_name.to_string(),
-                port: port_name.to_string(),
-                master: true,
-            };
+    let sender = sender.clone();
 
-            let msg: Message = MessageOutput::Master(output).into();
-            sender.emit(msg);
+    let _ = queue.push(move |session| {
+        let queue = session.queue.clone();
 
-            let client = Box::new(info.into());
-            let msg: Message = MessageClient::Changed(client).into();
-            sender.emit(msg);
+        session.context.introspect().get_sink_info_by_name(&name, move |info| if let ListResult::Item(info) = info {
+            emit_master(info, &queue, &sender);
         });
     });
 }
 
-fn handle_sink_change(sender: &Sender<Message>, context: &WeakContext) {
-    let Some(introspect) = try_introspect(context) else { return };
+/// Reports the default sink, it's a new client for the mixer if the default sink has changed.
+fn emit_master(info: &SinkInfo, queue: &Queue, sender: &Sender<Message>) {
+    let index = info.index;
+    let client: Box<OutputClient> = Box::new(info.into());
+
+    let output = match info.name.as_ref() {
+        // Sinks without ports can't be picked in the output switcher
+        Some(output_name) => info.active_port.as_ref().and_then(|p| p.name.as_ref()).map(|port_name| Ok(Output {
+            name: output_name.to_string(),
+            port: port_name.to_string(),
+            master: true,
+        })),
+        None => Some(Err(PulseError::NamelessSink(index))),
+    };
 
-    introspect.get_sink_info_by_index(0, {
-        let sender = sender.clone();
+    let sender = sender.clone();
 
-        move |info| if let ListResult::Item(info) = info {
-            let client = Box::new(info.into());
-            let msg: Message = MessageClient::Changed(client).into();
+    let _ = queue.push(move |session| {
+        let msg: Message = match std::mem::replace(&mut session.master, index) == index {
+            true  => MessageClient::Changed(client).into(),
+            false => MessageClient::New(client).into(),
+        };
 
-            sender.emit(msg);
+        sender.emit(msg);
+
+        match output {
+            Some(Ok(output)) => sender.emit(Message::from(MessageOutput::Master(output))),
+            Some(Err(e)) => sender.emit(Message::Error(e.into())),
+            None => {},
         }
     });
 }
 
-fn handle_sink_input_change(sender: &Sender<Message>, context: &WeakContext, peakers: &WeakPeakers, op: Operation, i: u32) {
-    let Some(introspect) = try_introspect(context) else { return };
+fn handle_sink_input_change(session: &mut Session, sender: &Sender<Message>, op: Operation, i: u32) {
+    let queue = session.queue.clone();
+    let sender = sender.clone();
 
     match op {
         Operation::New => {
-            introspect.get_sink_input_info(i, {
-                let sender = sender.clone();
-                let context = context.clone();
-                let peakers = peakers.clone();
-
-                move |info| add_sink_input(info, &context, &sender, &peakers)
-            });
+            session.context.introspect().get_sink_input_info(i, move |info| add_sink_input(info, &queue, &sender));
         },
         Operation::Removed => {
-            if let Some(peakers) = peakers.upgrade() {
-                let guard = peakers.lock();
-                let mut peakers = guard.borrow_mut();
-
-                if let Some(pos) = peakers.iter().position(|stream| stream.get_index() == Some(i)) {
-                    peakers.remove(pos);
-                }
-            }
+            session.peakers.remove(i);
 
             let msg: Message = MessageClient::Removed(i).into();
             sender.emit(msg);
         },
         Operation::Changed => {
-            introspect.get_sink_input_info(i, {
-                let sender = sender.clone();
-
-                move |info| {
-                    if let ListResult::Item(info) = info {
-                        let client = Box::new(info.into());
-                        let msg: Message = MessageClient::Changed(client).into();
-
-                        sender.emit(msg);
-                    };
-                }
+            session.context.introspect().get_sink_input_info(i, move |info| {
+                if let ListResult::Item(info) = info {
+                    let probe = Probe::from(info);
+                    let _ = queue.push({
+                        let sender = sender.clone();
+                        move |session| session.sync_peaker(&sender, &probe)
+                    });
+
+                    let client = Box::new(info.into());
+                    let msg: Message = MessageClient::Changed(client).into();
+
+                    sender.emit(msg);
+                };
             });
         },
     }
 }
 
-fn subscribe_callback(sender: &Sender<Message>, context: &WeakContext, peakers: &WeakPeakers, facility: Option<Facility>, op: Option<Operation>, i: u32) {
+fn subscribe_callback(queue: &Queue, sender: &Sender<Message>, facility: Option<Facility>, op: Option<Operation>, i: u32) {
     let Some(op) = op else { return };
 
-    match facility {
+    let sender = sender.clone();
+
+    let _ = match facility {
         Some(Facility::SinkInput) => {
-            handle_sink_input_change(sender, context, peakers, op, i);
+            queue.push(move |session| handle_sink_input_change(session, &sender, op, i))
         },
         Some(Facility::Sink) => {
-            handle_sink_change(sender, context);
+            queue.push(move |session| handle_sink_change(session, &sender, op, i))
         }
         Some(Facility::Server) => {
-            handle_server_change(sender, context);
+            queue.push(move |session| handle_server_change(session, &sender))
         },
-        _ => {},
-    }
-
+        _ => Ok(()),
+    };
 }
 
-fn state_callback(context: &WeakContext, state: &Weak<AtomicU8>, sender: &Sender<Message>) {
-    let Some(context) = context.upgrade() else { return };
-
-    let guard = context.lock();
-    let new_state = guard.borrow().get_state();
+fn state_callback(session: &mut Session, state: &AtomicU8, sender: &Sender<Message>) {
+    let new_state = session.context.get_state();
 
-    if let Some(state) = state.upgrade() {
-        state.store(new_state as u8, Ordering::Release);
+    // Every change in a single dispatch is read as the latest state
+    if state.swap(new_state as u8, Ordering::AcqRel) == new_state as u8 {
+        return
     }
 
     match new_state {
         State::Ready => sender.emit(Message::Ready),
         State::Failed => {
-            let e = PulseError::from(guard.borrow().errno());
+            let e = PulseError::from(session.context.errno());
             sender.emit(Message::Disconnected(Some(e.into())));
-        },
-        State::Terminated => sender.emit(Message::Disconnected(None)),
-        _ => {},
-    }
-}
-
-fn try_introspect(context: &WeakContext) -> Option<Introspector> {
-    let context = context.upgrade()?;
 
-    let guard = context.lock();
-    let context = guard.borrow_mut();
-
-    Some(context.introspect())
-}
-
-#[derive(Deref)]
-struct ContextRef<'a> {
-    #[deref]
-    context: MutexGuard<'a, RefCell<Context>>,
-    lock: &'a watch::Sender<Lock>,
-    thread: MutexGuard<'a, Thread>,
-}
-
-impl ContextRef<'_> {
-    fn introspect(&self) -> Introspector {
-        let context = self.borrow();
-        context.introspect()
-    }
-}
-
-impl Drop for ContextRef<'_> {
-    #[inline]
-    fn drop(&mut self) {
-        self.lock.send_replace(Lock::Unlocked);
-        self.thread.unpark();
-    }
-}
-
-#[derive(From)]
-struct Duration(std::time::Duration);
+            session.closed.cancel();
+        },
+        State::Terminated => {
+            sender.emit(Message::Disconnected(None));
 
-impl From<&Duration> for Option<libpulse_binding::time::MicroSeconds> {
-    #[inline]
-    fn from(d: &Duration) -> Self {
-        match d.0.is_zero() {
-            false => Some(libpulse_binding::time::MicroSeconds(d.0.as_micros() as u64)),
-            true => None,
-        }
+            session.closed.cancel();
+        },
+        _ => {},
     }
 }
 
@@ -702,6 +918,13 @@ impl From<VolumeLevels> for ChannelVolumes {
     }
 }
 
+/// Highest volume Pulse Audio accepts, as a slider position.
+fn max_volume() -> f64 {
+    use libpulse_binding::volume::{Volume, VolumeLinear};
+
+    crate::curve::config().curve.position(VolumeLinear::from(Volume::MAX).0)
+}
+
 impl Volume {
     fn pulse_linear(&self) -> f64 {
         use libpulse_binding::volume::{Volume, VolumeLinear};
@@ -731,10 +954,10 @@ impl <'a> From<&SinkInputInfo<'a>> for OutputClient {
         let icon = sink_input.proplist.get_str("application.icon_name");
         let process = sink_input.proplist.get_str("application.process.id")
             .and_then(|b| b.parse::<u32>().ok());
-
-        // This would be the correct approach, but things get weird after 255%
-        // static VOLUME_MAX: OnceLock<f64> = OnceLock::new();
-        // let max = *VOLUME_MAX.get_or_init(|| VolumeLinear::from(libpulse_binding::volume::Volume::ui_max()).0);
+        let binary = sink_input.proplist.get_str("application.process.binary");
+        let app_id = sink_input.proplist.get_str("application.flatpak.id")
+            .or_else(|| sink_input.proplist.get_str("pipewire.access.portal.app_id"));
+        let entry = sink_input.proplist.get_str("application.id");
 
         let volume = Volume {
             levels: {
@@ -746,18 +969,21 @@ impl <'a> From<&SinkInputInfo<'a>> for OutputClient {
 
                 VolumeLevels(SmallVec::from_slice(&levels[..sink_input.volume.len() as usize]))
             },
-            percent: &Volume::pulse_linear,
-            set_percent: &Volume::set_pulse_linear,
+            amplitude: &Volume::pulse_linear,
+            set_amplitude: &Volume::set_pulse_linear,
         };
 
         OutputClient {
             id: sink_input.index,
             process,
+            binary,
+            app_id,
+            entry,
             name,
             description,
             icon,
             volume,
-            max_volume: 2.55,
+            max_volume: max_volume(),
             muted: sink_input.mute,
             corked: sink_input.corked,
             kind: Kind::Out | Kind::Software,
@@ -765,6 +991,20 @@ impl <'a> From<&SinkInputInfo<'a>> for OutputClient {
     }
 }
 
+impl <'a> TryFrom<&SinkInfo<'a>> for Sink {
+    type Error = PulseError;
+
+    fn try_from(info: &SinkInfo<'a>) -> Result<Self, Self::Error> {
+        let name = info.name.as_ref().ok_or(PulseError::NamelessSink(info.index))?;
+
+        Ok(Sink {
+            id: info.index,
+            name: name.to_string(),
+            description: info.description.as_deref().unwrap_or(name).to_owned(),
+        })
+    }
+}
+
 impl <'a> From<&SinkInfo<'a>> for OutputClient {
     fn from(sink: &SinkInfo<'a>) -> Self {
         let description = sink.active_port
@@ -782,27 +1022,534 @@ impl <'a> From<&SinkInfo<'a>> for OutputClient {
                 };
                 VolumeLevels(SmallVec::from_slice(&levels[..sink.volume.len() as usize]))
             },
-            percent: &|v: &Volume| {
-                use libpulse_binding::volume::Volume;
-
-                *v.levels.iter().max().unwrap() as f64 / Volume::NORMAL.0 as f64
-            },
-            set_percent: &|v: &mut Volume, p: f64| {
-                v.levels.fill((libpulse_binding::volume::Volume::NORMAL.0 as f64 * p) as u32);
-            },
+            amplitude: &Volume::pulse_linear,
+            set_amplitude: &Volume::set_pulse_linear,
         };
 
         OutputClient {
-            id: 0,
+            id: sink.index,
             process: None,
+            binary: None,
+            app_id: None,
+            entry: None,
             name: "Master".to_owned(),
             description,
             icon: None,
             volume,
-            max_volume: 2.55,
+            max_volume: max_volume(),
             muted: sink.mute,
             corked: false,
             kind: Kind::Out | Kind::Hardware,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    //! Integration tests against a private Pulse Audio daemon with a couple of null sinks.
+    //!
+    //! Tests are ignored by default, `make test` runs them with `--include-ignored`.
+    //! They are skipped if `pulseaudio`, `pacat` or `pactl` can't be found.
+    //! `MIXXC_TEST_PULSE_SERVER` runs them against an already running server instead,
+    //! for example `pipewire-pulse` with a temporary runtime directory.
+
+    use std::fs::File;
+    use std::path::PathBuf;
+    use std::process::{Child, Command, Stdio};
+    use std::sync::atomic::AtomicU32;
+    use std::sync::mpsc;
+    use std::thread::JoinHandle;
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    use crate::app::CommandMessage;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    const SOFTWARE: Kind = Kind::Software.union(Kind::Out);
+    const HARDWARE: Kind = Kind::Hardware.union(Kind::Out);
+
+    // Server address is passed through the environment, so only one test can talk to it at a time
+    static SERIAL: Mutex<()> = parking_lot::const_mutex(());
+
+    struct Daemon {
+        process: Option<Child>,
+        dir: Option<PathBuf>,
+        server: String,
+    }
+
+    impl Daemon {
+        fn spawn() -> Option<Self> {
+            if let Ok(server) = std::env::var("MIXXC_TEST_PULSE_SERVER") {
+                return Some(Daemon { process: None, dir: None, server })
+            }
+
+            static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+            let dir = std::env::temp_dir().join(format!("mixxc-pulse-{}-{}", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)));
+            std::fs::create_dir_all(&dir).unwrap();
+
+            let socket = dir.join("native");
+
+            let process = Command::new("pulseaudio")
+                .args(["-n", "--daemonize=no", "--use-pid-file=no", "--exit-idle-time=-1", "--log-target=stderr"])
+                .arg(format!("--load=module-native-protocol-unix socket={} auth-anonymous=1", socket.display()))
+                .arg("--load=module-null-sink sink_name=first")
+                .arg("--load=module-null-sink sink_name=second")
+                .env("HOME", &dir)
+                .env("XDG_CONFIG_HOME", &dir)
+                .env("PULSE_RUNTIME_PATH", &dir)
+                .env("PULSE_STATE_PATH", &dir)
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn();
+
+            let daemon = Daemon {
+                server: format!("unix:{}", socket.display()),
+                dir: Some(dir),
+                process: match process {
+                    Ok(process) => Some(process),
+                    Err(e) => {
+                        eprintln!("Skipping, unable to start pulseaudio: {e}");
+                        return None
+                    }
+                },
+            };
+
+            let start = Instant::now();
+
+            while !socket.exists() {
+                assert!(start.elapsed() < TIMEOUT, "pulseaudio didn't open a socket in time");
+                std::thread::sleep(Duration::from_millis(10));
+            }
+
+            Some(daemon)
+        }
+
+        /// Plays noise to the sink with a given application name.
+        fn play(&self, name: &str, sink: &str) -> Option<Player> {
+            let process = Command::new("pacat")
+                .args(["--playback", "--format=s16le", "--channels=2", "--rate=8000"])
+                .arg(format!("--server={}", self.server))
+                .arg(format!("--device={sink}"))
+                .arg(format!("--client-name={name}"))
+                .arg(format!("--property=application.name={name}"))
+                .stdin(File::open("/dev/urandom").unwrap())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn();
+
+            match process {
+                Ok(process) => Some(Player(process)),
+                Err(e) => {
+                    eprintln!("Skipping, unable to start pacat: {e}");
+                    None
+                }
+            }
+        }
+
+        fn default_sink(&self) -> Option<String> {
+            self.pactl(&["get-default-sink"])
+        }
+
+        fn pactl(&self, args: &[&str]) -> Option<String> {
+            let output = Command::new("pactl")
+                .arg(format!("--server={}", self.server))
+                .args(args)
+                .output();
+
+            match output {
+                Ok(output) => Some(String::from_utf8_lossy(&output.stdout).trim().to_owned()),
+                Err(e) => {
+                    eprintln!("Skipping, unable to run pactl: {e}");
+                    None
+                }
+            }
+        }
+    }
+
+    impl Drop for Daemon {
+        fn drop(&mut self) {
+            if let Some(process) = &mut self.process {
+                let _ = process.kill();
+                let _ = process.wait();
+            }
+
+            if let Some(dir) = &self.dir {
+                let _ = std::fs::remove_dir_all(dir);
+            }
+        }
+    }
+
+    struct Player(Child);
+
+    impl Drop for Player {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    struct Connection {
+        pulse: Arc<Pulse>,
+        sender: relm4::Sender<CommandMessage>,
+        messages: mpsc::Receiver<Message>,
+        thread: Option<JoinHandle<Result<(), Error>>>,
+    }
+
+    impl Connection {
+        fn new(daemon: &Daemon, peak: PeakConfig) -> Self {
+            std::env::set_var("PULSE_SERVER", &daemon.server);
+
+            let pulse = Arc::new(Pulse::new(peak));
+            let (sender, receiver) = relm4::channel::<CommandMessage>();
+
+            // Relm4 receiver can't time out, so messages are moved into a channel that can
+            let (tx, messages) = mpsc::channel();
+
+            std::thread::spawn(move || {
+                while let Some(CommandMessage::Server(message)) = receiver.recv_sync() {
+                    if tx.send(message).is_err() {
+                        break
+                    }
+                }
+            });
+
+            let thread = std::thread::spawn({
+                let pulse = pulse.clone();
+                let sender = sender.clone();
+
+                move || pulse.connect(&sender)
+            });
+
+            let connection = Connection { pulse, sender, messages, thread: Some(thread) };
+            connection.expect(|message| matches!(message, Message::Ready).then_some(()));
+
+            connection
+        }
+
+        /// Waits for a message that satisfies the filter, skipping everything else.
+        fn expect<T>(&self, mut filter: impl FnMut(Message) -> Option<T>) -> T {
+            let start = Instant::now();
+
+            loop {
+                let timeout = TIMEOUT.checked_sub(start.elapsed()).unwrap_or_default();
+
+                match self.messages.recv_timeout(timeout) {
+                    Ok(message) => if let Some(value) = filter(message) {
+                        return value
+                    },
+                    Err(_) => panic!("expected message never arrived"),
+                }
+            }
+        }
+
+        fn new_client(&self, name: &str) -> Box<OutputClient> {
+            self.expect(|message| match message {
+                Message::OutputClient(MessageClient::New(client)) if client.name == name => Some(client),
+                _ => None,
+            })
+        }
+
+        fn changed_client(&self, id: u32, mut filter: impl FnMut(&OutputClient) -> bool) -> Box<OutputClient> {
+            self.expect(|message| match message {
+                Message::OutputClient(MessageClient::Changed(client)) if client.id == id && filter(&client) => Some(client),
+                _ => None,
+            })
+        }
+    }
+
+    impl Drop for Connection {
+        fn drop(&mut self) {
+            if self.pulse.is_connected() {
+                self.pulse.disconnect();
+            }
+
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        glib::MainContext::new().block_on(future)
+    }
+
+    fn setup() -> Option<(parking_lot::MutexGuard<'static, ()>, Daemon)> {
+        let guard = SERIAL.lock();
+        Daemon::spawn().map(|daemon| (guard, daemon))
+    }
+
+    #[test]
+    #[ignore = "needs a Pulse Audio server"]
+    fn connection() {
+        let Some((_guard, daemon)) = setup() else { return };
+
+        let mut connection = Connection::new(&daemon, PeakConfig::default());
+
+        assert!(matches!(connection.pulse.connect(&connection.sender), Err(Error::AlreadyConnected)));
+
+        connection.pulse.disconnect();
+        connection.expect(|message| matches!(message, Message::Disconnected(None)).then_some(()));
+
+        let result = connection.thread.take().unwrap().join().unwrap();
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    #[ignore = "needs a Pulse Audio server"]
+    fn software_clients() {
+        let Some((_guard, daemon)) = setup() else { return };
+        let Some(_player) = daemon.play("Player", "first") else { return };
+
+        let connection = Connection::new(&daemon, PeakConfig::default());
+
+        // Stream might take a moment to appear on the server
+        let client = (0..100).find_map(|_| {
+            block_on(connection.pulse.request_software(&connection.sender)).unwrap();
+
+            let start = Instant::now();
+            while start.elapsed() < Duration::from_millis(50) {
+                if let Ok(Message::OutputClient(MessageClient::New(client))) = connection.messages.recv_timeout(Duration::from_millis(10)) {
+                    return Some(client)
+                }
+            }
+
+            None
+        }).expect("player appears on the server");
+
+        assert_eq!(client.name, "Player");
+        assert_eq!(client.volume.levels.len(), 2);
+        assert!(client.kind.contains(SOFTWARE));
+    }
+
+    #[test]
+    #[ignore = "needs a Pulse Audio server"]
+    fn subscription() {
+        let Some((_guard, daemon)) = setup() else { return };
+
+        let connection = Connection::new(&daemon, PeakConfig::default());
+        block_on(connection.pulse.subscribe(SOFTWARE, &connection.sender)).unwrap();
+
+        let Some(player) = daemon.play("Subscriber", "first") else { return };
+        let client = connection.new_client("Subscriber");
+
+        let mut volume = client.volume.clone();
+        volume.set_percent(0.5);
+
+        block_on(connection.pulse.set_volume([client.id], SOFTWARE, volume.levels.clone())).unwrap();
+        let changed = connection.changed_client(client.id, |changed| changed.volume == volume);
+        assert!((changed.volume.percent() - 0.5).abs() < 0.01);
+
+        block_on(connection.pulse.set_mute([client.id], SOFTWARE, true)).unwrap();
+        connection.changed_client(client.id, |changed| changed.muted);
+
+        drop(player);
+
+        connection.expect(|message| matches!(message, Message::OutputClient(MessageClient::Removed(id)) if id == client.id).then_some(()));
+    }
+
+    #[test]
+    #[ignore = "needs a Pulse Audio server"]
+    fn master_and_outputs() {
+        let Some((_guard, daemon)) = setup() else { return };
+
+        let connection = Connection::new(&daemon, PeakConfig::default());
+
+        block_on(connection.pulse.request_master(&connection.sender)).unwrap();
+
+        let master = connection.new_client("Master");
+        assert!(master.kind.contains(HARDWARE));
+
+        block_on(connection.pulse.subscribe(HARDWARE, &connection.sender)).unwrap();
+
+        let mut volume = master.volume.clone();
+        volume.set_percent(0.3);
+
+        block_on(connection.pulse.set_volume([master.id], HARDWARE, volume.levels.clone())).unwrap();
+        connection.changed_client(master.id, |changed| changed.volume == volume);
+
+        // Null sinks have no ports, so the server is asked directly
+        if daemon.process.is_some() {
+            block_on(connection.pulse.set_output_by_name("second", None)).unwrap();
+
+            let start = Instant::now();
+
+            loop {
+                let Some(sink) = daemon.default_sink() else { return };
+
+                if sink == "second" {
+                    break
+                }
+
+                assert!(start.elapsed() < TIMEOUT, "default sink didn't change");
+                std::thread::sleep(Duration::from_millis(10));
+            }
+
+            // Master follows the default sink
+            let second = connection.expect(|message| match message {
+                Message::OutputClient(MessageClient::New(client)) if client.kind.contains(HARDWARE) => Some(client),
+                _ => None,
+            });
+
+            assert_ne!(second.id, master.id);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a Pulse Audio server"]
+    fn sinks() {
+        let Some((_guard, daemon)) = setup() else { return };
+
+        let connection = Connection::new(&daemon, PeakConfig::default());
+
+        block_on(connection.pulse.request_outputs(&connection.sender)).unwrap();
+
+        // Null sinks have no ports, but clients can still be moved to them
+        connection.expect(|message| match message {
+            Message::Output(MessageOutput::Sink(sink)) if sink.name == "second" => Some(()),
+            _ => None,
+        });
+
+        block_on(connection.pulse.subscribe(SOFTWARE, &connection.sender)).unwrap();
+
+        let Some(module) = daemon.pactl(&["load-module", "module-null-sink", "sink_name=third"]) else { return };
+
+        let third = connection.expect(|message| match message {
+            Message::Output(MessageOutput::Sink(sink)) if sink.name == "third" => Some(sink),
+            _ => None,
+        });
+
+        daemon.pactl(&["unload-module", &module]);
+
+        connection.expect(|message| matches!(message, Message::Output(MessageOutput::SinkRemoved(id)) if id == third.id).then_some(()));
+    }
+
+    #[test]
+    #[ignore = "needs a Pulse Audio server"]
+    fn peaks() {
+        let Some((_guard, daemon)) = setup() else { return };
+
+        let connection = Connection::new(&daemon, PeakConfig {
+            per_channel: true,
+            rate: 25,
+            pause_corked: false,
+        });
+
+        block_on(connection.pulse.subscribe(SOFTWARE, &connection.sender)).unwrap();
+
+        let Some(_player) = daemon.play("Peaker", "first") else { return };
+        let client = connection.new_client("Peaker");
+
+        let peaks = connection.expect(|message| match message {
+            Message::OutputClient(MessageClient::Peak(id, peaks)) if id == client.id => Some(peaks),
+            _ => None,
+        });
+
+        assert_eq!(peaks.len(), 2);
+        assert!(peaks.iter().all(|peak| (0.0..=1.0).contains(peak)));
+
+        block_on(connection.pulse.set_peaking(false, &connection.sender)).unwrap();
+        let idle = block_on(connection.pulse.run(|session| session.peakers.streams.is_empty())).unwrap();
+        assert!(idle);
+    }
+
+    #[test]
+    #[ignore = "needs a Pulse Audio server"]
+    fn concurrent_requests() {
+        let Some((_guard, daemon)) = setup() else { return };
+        let Some(_player) = daemon.play("Contender", "first") else { return };
+
+        let connection = Connection::new(&daemon, PeakConfig::default());
+        block_on(connection.pulse.subscribe(SOFTWARE, &connection.sender)).unwrap();
+
+        let client = (0..100).find_map(|_| {
+            block_on(connection.pulse.request_software(&connection.sender)).unwrap();
+            connection.messages.recv_timeout(Duration::from_millis(50)).ok().and_then(|message| match message {
+                Message::OutputClient(MessageClient::New(client)) => Some(client),
+                _ => None,
+            })
+        }).expect("player appears on the server");
+
+        // Every request has to take the mainloop lock from the connection thread
+        let threads: Vec<_> = (1..=4u32).map(|i| {
+            let pulse = connection.pulse.clone();
+            let mut volume = client.volume.clone();
+            let id = client.id;
+
+            std::thread::spawn(move || for step in 0..20 {
+                volume.set_percent((i * 20 + step) as f64 / 200.0);
+                block_on(pulse.set_volume([id], SOFTWARE, volume.levels.clone())).unwrap();
+            })
+        }).collect();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        // Still responsive after all of that
+        block_on(connection.pulse.set_mute([client.id], SOFTWARE, true)).unwrap();
+        connection.changed_client(client.id, |changed| changed.muted);
+    }
+
+    #[test]
+    #[ignore = "needs a Pulse Audio server"]
+    fn disconnect_in_flight() {
+        let Some((_guard, daemon)) = setup() else { return };
+
+        let connection = Connection::new(&daemon, PeakConfig::default());
+
+        // Mainloop is held up, so the request and the disconnect are run back to back
+        let (release, hold) = mpsc::channel::<()>();
+        connection.pulse.execute(move |_| { let _ = hold.recv(); }).unwrap();
+
+        let (done, result) = mpsc::channel();
+
+        std::thread::spawn({
+            let pulse = connection.pulse.clone();
+            let levels = VolumeLevels(smallvec::smallvec![0x8000, 0x8000]);
+
+            move || done.send(block_on(pulse.set_volume([u32::MAX - 1], SOFTWARE, levels)))
+        });
+
+        std::thread::sleep(Duration::from_millis(100));
+
+        let disconnect = std::thread::spawn({
+            let pulse = connection.pulse.clone();
+            move || pulse.disconnect()
+        });
+
+        std::thread::sleep(Duration::from_millis(100));
+        release.send(()).unwrap();
+
+        let result = result.recv_timeout(TIMEOUT).expect("request in flight returns after disconnect");
+        assert!(matches!(result, Err(Error::Pulse(PulseError::NotConnected))));
+
+        disconnect.join().unwrap();
+        connection.expect(|message| matches!(message, Message::Disconnected(None)).then_some(()));
+    }
+
+    #[test]
+    #[ignore = "needs a Pulse Audio server"]
+    fn failed_requests() {
+        let Some((_guard, daemon)) = setup() else { return };
+
+        let connection = Connection::new(&daemon, PeakConfig::default());
+
+        let levels = VolumeLevels(smallvec::smallvec![0x8000, 0x8000]);
+
+        let e = block_on(connection.pulse.set_volume([u32::MAX - 1], SOFTWARE, levels)).err();
+        assert!(matches!(e, Some(Error::Pulse(PulseError::Volume(id, _))) if id == u32::MAX - 1));
+
+        let e = block_on(connection.pulse.set_mute([u32::MAX - 1], HARDWARE, true)).err();
+        assert!(matches!(e, Some(Error::Pulse(PulseError::Mute(..)))));
+
+        let e = block_on(connection.pulse.set_output_by_name("missing", Some("nowhere"))).err();
+        assert!(matches!(e, Some(Error::Pulse(PulseError::Output(..)))));
+
+        // Failures don't break the connection
+        block_on(connection.pulse.request_master(&connection.sender)).unwrap();
+        connection.new_client("Master");
+    }
+}
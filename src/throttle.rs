@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::server::{Kind, VolumeLevels};
+
+// Shortest time between two volume writes to the same client
+pub const INTERVAL: Duration = Duration::from_millis(50);
+
+// How long changes from the server are considered echoes of our own writes
+const SETTLE: Duration = Duration::from_millis(1000);
+
+/// Client of the audio server, software and hardware ids can overlap.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Target {
+    pub id: u32,
+    pub hardware: bool,
+}
+
+impl Target {
+    pub fn new(id: u32, kind: Kind) -> Self {
+        Self { id, hardware: kind.contains(Kind::Hardware) }
+    }
+
+    pub fn kind(&self) -> Kind {
+        match self.hardware {
+            true  => Kind::Hardware | Kind::Out,
+            false => Kind::Software | Kind::Out,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Write {
+    /// Write can go to the server right away
+    Now,
+    /// Flush has to be scheduled after a delay
+    Later(Duration),
+    /// Value replaced the one that waits for an already scheduled flush
+    Queued,
+}
+
+struct Pending {
+    sent: Instant,
+    latest: VolumeLevels,
+    queued: bool,
+}
+
+/// Coalesces volume writes per client and recognizes their echoes.
+#[derive(Default)]
+pub struct Throttle {
+    writes: HashMap<Target, Pending>,
+}
+
+impl Throttle {
+    pub fn write(&mut self, target: Target, levels: VolumeLevels, now: Instant) -> Write {
+        let Some(pending) = self.writes.get_mut(&target) else {
+            self.writes.insert(target, Pending { sent: now, latest: levels, queued: false });
+            return Write::Now
+        };
+
+        pending.latest = levels;
+
+        let elapsed = now.duration_since(pending.sent);
+
+        match pending.queued {
+            true => Write::Queued,
+            false if elapsed >= INTERVAL => {
+                pending.sent = now;
+                Write::Now
+            },
+            false => {
+                pending.queued = true;
+                Write::Later(INTERVAL - elapsed)
+            }
+        }
+    }
+
+    /// Latest value that waited for the flush.
+    pub fn flush(&mut self, target: Target, now: Instant) -> Option<VolumeLevels> {
+        let pending = self.writes.get_mut(&target).filter(|pending| pending.queued)?;
+
+        pending.queued = false;
+        pending.sent = now;
+
+        Some(pending.latest.clone())
+    }
+
+    /// Latest written volume to show instead of the one reported by the server while writes are in flight.
+    pub fn echo(&mut self, target: Target, levels: &VolumeLevels, now: Instant) -> Option<VolumeLevels> {
+        let pending = self.writes.get(&target)?;
+
+        let settled = !pending.queued && (levels[..] == pending.latest[..] || now.duration_since(pending.sent) >= SETTLE);
+
+        match settled {
+            true  => { self.writes.remove(&target); None },
+            false => Some(pending.latest.clone()),
+        }
+    }
+
+    pub fn forget(&mut self, target: Target) {
+        self.writes.remove(&target);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET: Target = Target { id: 3, hardware: false };
+
+    fn levels(v: u32) -> VolumeLevels {
+        VolumeLevels(smallvec::smallvec![v, v])
+    }
+
+    #[test]
+    fn coalesces_writes() {
+        let mut throttle = Throttle::default();
+        let start = Instant::now();
+
+        assert_eq!(throttle.write(TARGET, levels(10), start), Write::Now);
+        assert_eq!(throttle.write(TARGET, levels(20), start + Duration::from_millis(10)), Write::Later(Duration::from_millis(40)));
+        assert_eq!(throttle.write(TARGET, levels(30), start + Duration::from_millis(20)), Write::Queued);
+
+        let flushed = throttle.flush(TARGET, start + INTERVAL).unwrap();
+        assert_eq!(flushed[..], levels(30)[..]);
+        assert!(throttle.flush(TARGET, start + INTERVAL).is_none());
+
+        assert_eq!(throttle.write(TARGET, levels(40), start + INTERVAL * 2), Write::Now);
+
+        // Other clients aren't held back
+        assert_eq!(throttle.write(Target { id: 3, hardware: true }, levels(10), start), Write::Now);
+    }
+
+    #[test]
+    fn suppresses_echoes() {
+        let mut throttle = Throttle::default();
+        let start = Instant::now();
+
+        throttle.write(TARGET, levels(10), start);
+        throttle.write(TARGET, levels(20), start + Duration::from_millis(10));
+
+        // Echo of the first write while the second one waits
+        let shown = throttle.echo(TARGET, &levels(10), start + Duration::from_millis(20)).unwrap();
+        assert_eq!(shown[..], levels(20)[..]);
+
+        throttle.flush(TARGET, start + INTERVAL);
+
+        // Echo of the last write ends the suppression
+        assert!(throttle.echo(TARGET, &levels(20), start + INTERVAL).is_none());
+        assert!(throttle.echo(TARGET, &levels(50), start + INTERVAL).is_none());
+    }
+
+    #[test]
+    fn settles_without_echo() {
+        let mut throttle = Throttle::default();
+        let start = Instant::now();
+
+        throttle.write(TARGET, levels(10), start);
+
+        assert!(throttle.echo(TARGET, &levels(50), start + SETTLE).is_none());
+    }
+}
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use relm4::{FactorySender, RelmWidgetExt};
+
+use gtk::{Align, Orientation};
+use gtk::pango::EllipsizeMode;
+use gtk::prelude::{BoxExt, ButtonExt, WidgetExt};
+
+use smallvec::SmallVec;
+
+use crate::mpris::{Action, Media, Track};
+use crate::server::OutputClient;
+
+use super::sliderbox::{Slider, SliderMessage};
+
+/// Media player attached to a slider and its controls.
+#[derive(Default)]
+pub struct MediaControls {
+    pub bus: Option<Arc<str>>,
+    pids: SmallVec<[u32; 3]>,
+    names: SmallVec<[String; 3]>,
+    widgets: Option<Widgets>,
+}
+
+struct Widgets {
+    root: gtk::Box,
+    title: gtk::Label,
+    previous: gtk::Button,
+    play: gtk::Button,
+    next: gtk::Button,
+}
+
+impl MediaControls {
+    pub fn add_client(&mut self, client: &OutputClient) {
+        self.pids.extend(client.process);
+
+        let names = [Some(&client.name), client.binary.as_ref(), client.app_id.as_ref()];
+
+        for name in names.into_iter().flatten().filter(|name| !name.is_empty()) {
+            let name = name.to_lowercase();
+
+            if !self.names.contains(&name) {
+                self.names.push(name);
+            }
+        }
+    }
+
+    /// Player is matched by process, or by desktop entry against application name, binary and Flatpak ID.
+    pub fn plays(&self, media: &Media) -> bool {
+        if media.pid.is_some_and(|pid| self.pids.contains(&pid)) {
+            return true
+        }
+
+        media.entry.as_ref().is_some_and(|entry| self.names.contains(&entry.to_lowercase()))
+    }
+
+    pub fn build(&mut self, parent: &gtk::Box, sender: &FactorySender<Slider>) {
+        let button = |icon: &str, class: &str, action: Action| {
+            let button = gtk::Button::from_icon_name(icon);
+            button.add_css_class(class);
+
+            let sender = sender.clone();
+            button.connect_clicked(move |_| sender.input(SliderMessage::MediaAction(action)));
+
+            button
+        };
+
+        let widgets = Widgets {
+            root: gtk::Box::new(Orientation::Horizontal, 0),
+            title: gtk::Label::new(None),
+            previous: button("media-skip-backward-symbolic", "previous", Action::Previous),
+            play: button("media-playback-start-symbolic", "play", Action::PlayPause),
+            next: button("media-skip-forward-symbolic", "next", Action::Next),
+        };
+
+        widgets.root.add_css_class("media");
+        widgets.root.set_visible(false);
+
+        widgets.title.add_css_class("title");
+        widgets.title.set_ellipsize(EllipsizeMode::End);
+        widgets.title.set_hexpand(true);
+        widgets.title.set_halign(Align::Start);
+
+        widgets.root.append(&widgets.title);
+        widgets.root.append(&widgets.previous);
+        widgets.root.append(&widgets.play);
+        widgets.root.append(&widgets.next);
+
+        parent.append(&widgets.root);
+
+        self.widgets = Some(widgets);
+    }
+
+    /// Shows the track, controls are hidden without one.
+    pub fn set_track(&self, track: Option<&Track>) {
+        let Some(widgets) = &self.widgets else { return };
+
+        widgets.root.set_visible(track.is_some());
+
+        let Some(track) = track else { return };
+
+        widgets.title.set_label(&track.title);
+        widgets.title.set_tooltip(&track.title);
+
+        widgets.play.set_icon_name(match track.playing {
+            true  => "media-playback-pause-symbolic",
+            false => "media-playback-start-symbolic",
+        });
+
+        widgets.root.set_class_active("playing", track.playing);
+
+        widgets.previous.set_sensitive(track.can_previous);
+        widgets.next.set_sensitive(track.can_next);
+    }
+}
@@ -0,0 +1,133 @@
+use relm4::FactorySender;
+
+use gtk::gio;
+use gtk::glib::{self, variant::ToVariant};
+use gtk::prelude::{ActionMapExt, PopoverExt, WidgetExt};
+
+use super::sliderbox::{Slider, SliderMessage};
+
+/// Action picked from the context menu of a slider.
+#[derive(Debug, Clone)]
+pub enum MenuAction {
+    Solo,
+    Reset,
+    /// Name of the output to move the clients to
+    Move(String),
+    Hide,
+    Pin,
+    Copy,
+    OpenMixer,
+}
+
+/// Context menu of a slider, opened with a right click.
+#[derive(Default)]
+pub struct SliderMenu {
+    popover: Option<gtk::PopoverMenu>,
+    pin: Option<gio::SimpleAction>,
+    solo: Option<gio::SimpleAction>,
+}
+
+impl SliderMenu {
+    /// Outputs are shared by every slider, so they stay up to date without rebuilding menus.
+    pub fn build(&mut self, parent: &gtk::Box, outputs: &gio::Menu, sender: &FactorySender<Slider>) {
+        let actions = gio::SimpleActionGroup::new();
+
+        let action = |name: &str, action: MenuAction| {
+            let sender = sender.clone();
+            let simple = gio::SimpleAction::new(name, None);
+
+            simple.connect_activate(move |_, _| sender.input(SliderMessage::Menu(action.clone())));
+            actions.add_action(&simple);
+        };
+
+        action("reset", MenuAction::Reset);
+        action("hide", MenuAction::Hide);
+        action("copy", MenuAction::Copy);
+        action("mixer", MenuAction::OpenMixer);
+
+        // State of toggles is set when the menu opens
+        let toggle = |name: &str, action: MenuAction| {
+            let sender = sender.clone();
+            let simple = gio::SimpleAction::new_stateful(name, None, &false.to_variant());
+
+            simple.connect_activate(move |_, _| sender.input(SliderMessage::Menu(action.clone())));
+            actions.add_action(&simple);
+
+            simple
+        };
+
+        let solo = toggle("solo", MenuAction::Solo);
+        let pin = toggle("pin", MenuAction::Pin);
+
+        let move_to = gio::SimpleAction::new("move", Some(glib::VariantTy::STRING));
+        move_to.connect_activate({
+            let sender = sender.clone();
+
+            move |_, output| if let Some(output) = output.and_then(|output| output.get::<String>()) {
+                sender.input(SliderMessage::Menu(MenuAction::Move(output)))
+            }
+        });
+        actions.add_action(&move_to);
+
+        parent.insert_action_group("slider", Some(&actions));
+
+        let volume = gio::Menu::new();
+        volume.append(Some("Solo"), Some("slider.solo"));
+        volume.append(Some("Reset to 100%"), Some("slider.reset"));
+        volume.append_submenu(Some("Move to Output"), outputs);
+
+        let everyone = gio::Menu::new();
+        everyone.append(Some("Mute All"), Some("app.mute-all"));
+        everyone.append(Some("Unmute All"), Some("app.unmute-all"));
+
+        let placement = gio::Menu::new();
+        placement.append(Some("Pin"), Some("slider.pin"));
+        placement.append(Some("Hide"), Some("slider.hide"));
+
+        let other = gio::Menu::new();
+        other.append(Some("Copy Stream Info"), Some("slider.copy"));
+        other.append(Some("Open in Pavucontrol"), Some("slider.mixer"));
+
+        let model = gio::Menu::new();
+        model.append_section(None, &volume);
+        model.append_section(None, &everyone);
+        model.append_section(None, &placement);
+        model.append_section(None, &other);
+
+        let popover = gtk::PopoverMenu::from_model(Some(&model));
+        popover.add_css_class("menu");
+        popover.set_has_arrow(false);
+        popover.set_parent(parent);
+
+        self.popover = Some(popover);
+        self.pin = Some(pin);
+        self.solo = Some(solo);
+    }
+
+    /// Opens the menu at a point of the slider.
+    pub fn popup(&self, x: f64, y: f64, pinned: bool, soloed: bool) {
+        let Some(popover) = &self.popover else { return };
+
+        for (action, flag) in [(&self.pin, pinned), (&self.solo, soloed)] {
+            if let Some(action) = action {
+                action.set_state(&flag.to_variant());
+            }
+        }
+
+        popover.set_pointing_to(Some(&gtk::gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+        popover.popup();
+    }
+
+    pub fn copy(&self, text: &str) {
+        if let Some(popover) = &self.popover {
+            popover.clipboard().set_text(text);
+        }
+    }
+
+    // Popover isn't a regular child and has to be detached before its slider goes away
+    pub fn unparent(&mut self) {
+        if let Some(popover) = self.popover.take() {
+            popover.unparent();
+        }
+    }
+}
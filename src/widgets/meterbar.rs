@@ -0,0 +1,200 @@
+use gtk::glib::{self, Object};
+use gtk::subclass::prelude::ObjectSubclassIsExt;
+
+mod imp {
+    use std::cell::{Cell, RefCell};
+
+    use gtk::glib;
+
+    use glib::Properties;
+    use glib::subclass::types::{ObjectSubclass, ObjectSubclassExt};
+    use glib::subclass::object::ObjectImpl;
+
+    use gtk::{Allocation, Orientation};
+    use gtk::prelude::{ObjectExt, WidgetExt};
+    use gtk::subclass::widget::{WidgetClassExt, WidgetImpl};
+    use gtk::subclass::prelude::DerivedObjectProperties;
+
+    use smallvec::SmallVec;
+
+    struct Channel {
+        level: f64,
+        hold: f64,
+        fill: gtk::Box,
+        marker: gtk::Box,
+    }
+
+    #[derive(Properties, Default)]
+    #[properties(wrapper_type = super::MeterBar)]
+    pub struct MeterBar {
+        #[property(get, set = Self::set_vertical)]
+        vertical: Cell<bool>,
+
+        #[property(get, set = Self::set_spacing)]
+        spacing: Cell<i32>,
+
+        channels: RefCell<SmallVec<[Channel; 2]>>,
+    }
+
+    impl MeterBar {
+        fn set_vertical(&self, vertical: bool) {
+            self.vertical.set(vertical);
+            self.obj().queue_resize();
+        }
+
+        fn set_spacing(&self, spacing: i32) {
+            self.spacing.set(spacing);
+            self.obj().queue_resize();
+        }
+
+        pub(super) fn set_values(&self, values: impl IntoIterator<Item = (f64, f64)>) {
+            let obj = self.obj();
+            let mut channels = self.channels.borrow_mut();
+
+            let mut n = 0;
+
+            for (i, (level, hold)) in values.into_iter().enumerate() {
+                if i == channels.len() {
+                    let fill = gtk::Box::builder().css_classes(["level"]).build();
+                    fill.set_parent(&*obj);
+
+                    let marker = gtk::Box::builder().css_classes(["hold"]).build();
+                    marker.set_parent(&*obj);
+
+                    channels.push(Channel { level, hold, fill, marker });
+
+                    obj.queue_resize();
+                }
+
+                channels[i].level = level;
+                channels[i].hold = hold;
+
+                n = i + 1;
+            }
+
+            for channel in channels.drain(n..) {
+                channel.fill.unparent();
+                channel.marker.unparent();
+
+                obj.queue_resize();
+            }
+
+            obj.queue_allocate();
+        }
+
+        fn orientation(&self) -> Orientation {
+            match self.vertical.get() {
+                true  => Orientation::Vertical,
+                false => Orientation::Horizontal,
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MeterBar {
+        const NAME: &'static str = "MeterBar";
+        type Type = super::MeterBar;
+        type ParentType = gtk::Widget;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.set_css_name("meter");
+        }
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for MeterBar {
+        fn dispose(&self) {
+            for channel in self.channels.borrow_mut().drain(..) {
+                channel.fill.unparent();
+                channel.marker.unparent();
+            }
+        }
+    }
+
+    impl WidgetImpl for MeterBar {
+        fn measure(&self, orientation: Orientation, _: i32) -> (i32, i32, i32, i32) {
+            let channels = self.channels.borrow();
+
+            let size = channels.iter()
+                .flat_map(|c| [&c.fill, &c.marker])
+                .map(|child| child.measure(orientation, -1).0)
+                .max()
+                .unwrap_or(0);
+
+            // Channels are stacked across the meter
+            let size = match orientation == self.orientation() || channels.is_empty() {
+                true  => size,
+                false => {
+                    let n = channels.len() as i32;
+                    size * n + self.spacing.get() * (n - 1)
+                }
+            };
+
+            (size, size, -1, -1)
+        }
+
+        fn size_allocate(&self, width: i32, height: i32, _: i32) {
+            let channels = self.channels.borrow();
+
+            if channels.is_empty() {
+                return
+            }
+
+            let vertical = self.vertical.get();
+            let orientation = self.orientation();
+
+            let (length, across) = match vertical {
+                true  => (height, width),
+                false => (width, height),
+            };
+
+            let n = channels.len() as i32;
+            let spacing = self.spacing.get();
+            let thickness = ((across - spacing * (n - 1)) / n).max(0);
+
+            for (i, channel) in channels.iter().enumerate() {
+                let offset = i as i32 * (thickness + spacing);
+
+                let level = (length as f64 * channel.level.clamp(0.0, 1.0)).round() as i32;
+
+                let marker = channel.marker.measure(orientation, -1).0.min(length);
+                let hold = (length as f64 * channel.hold.clamp(0.0, 1.0)).round() as i32;
+                let hold = (hold - marker).clamp(0, length - marker);
+
+                channel.marker.set_child_visible(channel.hold > 0.0);
+
+                match vertical {
+                    true => {
+                        channel.fill.size_allocate(&Allocation::new(offset, height - level, thickness, level), -1);
+                        channel.marker.size_allocate(&Allocation::new(offset, height - hold - marker, thickness, marker), -1);
+                    }
+                    false => {
+                        channel.fill.size_allocate(&Allocation::new(0, offset, level, thickness), -1);
+                        channel.marker.size_allocate(&Allocation::new(hold, offset, marker, thickness), -1);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// A peak meter with a peak-hold marker for every channel.
+// CSS nodes: meter > box.level, meter > box.hold
+glib::wrapper! {
+    pub struct MeterBar(ObjectSubclass<imp::MeterBar>)
+        @extends gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget;
+}
+
+impl MeterBar {
+    pub fn default() -> Self {
+        Object::builder()
+            .property("spacing", 1)
+            .build()
+    }
+
+    /// Level and peak-hold positions (0.0 - 1.0) for each channel.
+    pub fn set_values(&self, values: impl IntoIterator<Item = (f64, f64)>) {
+        self.imp().set_values(values)
+    }
+}
@@ -1,5 +1,11 @@
 pub mod sliderbox;
 pub mod switchbox;
+pub mod meterbar;
+pub mod stream;
+pub mod menu;
+
+#[cfg(feature = "Mpris")]
+pub mod media;
 
 pub enum GrowthDirection {
     TopLeft,
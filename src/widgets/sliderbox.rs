@@ -1,4 +1,6 @@
 use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt::Write;
 use std::time::{Duration, Instant};
 
 use relm4::Sender;
@@ -9,37 +11,163 @@ use relm4::prelude::{DynamicIndex, FactoryComponent};
 
 use gtk::{Orientation, Align, Justification};
 use gtk::pango::EllipsizeMode;
-use gtk::glib::{self, Object, object::Cast, ControlFlow};
-use gtk::prelude::{BoxExt, GtkWindowExt, GestureSingleExt, OrientableExt, RangeExt, WidgetExt, WidgetExtManual};
+use gtk::gio;
+use gtk::glib::{self, Object, object::Cast, variant::ToVariant, ControlFlow};
+use gtk::prelude::{BoxExt, ButtonExt, EventControllerExt, GtkWindowExt, GestureSingleExt, ObjectExt, OrientableExt, RangeExt, ScaleExt, WidgetExt, WidgetExtManual};
+use gtk::gdk::prelude::{DeviceExt, DisplayExt, SeatExt};
 
 use smallvec::SmallVec;
 
 use crate::anchor::Anchor;
+use crate::curve;
 use crate::app::ElementMessage;
-use crate::server::{self, OutputClient, Volume};
+use crate::error::CLIError;
+use crate::meter::Meters;
+use crate::server::{self, OutputClient, Peaks, Sink, Volume, VolumeLevels};
 
 use super::GrowthDirection;
+use super::menu::{MenuAction, SliderMenu};
+use super::meterbar::MeterBar;
+use super::stream::{Stream, StreamInit, StreamMessage, StreamOutput};
+
+// Peak meter animation step, meter is only animated while there is something to show
+const METER_TICK: Duration = Duration::from_millis(16);
+
+// How long `.moved` class stays on a slider after it was reordered
+const MOVED_DELAY: Duration = Duration::from_millis(300);
+
+// How long `.error` class stays on a slider after the server refused a change
+pub(super) const ERROR_DELAY: Duration = Duration::from_millis(1000);
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Sort {
+    #[default]
+    Arrival,
+    Name,
+    Activity,
+    Volume,
+    Priority,
+}
+
+/// What clients have in common to be merged into a single slider.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Group {
+    Process,
+    Name,
+    Binary,
+    Flatpak,
+}
+
+impl TryFrom<&String> for Group {
+    type Error = CLIError;
+
+    fn try_from(s: &String) -> Result<Self, Self::Error> {
+        match s.as_bytes().first().map(u8::to_ascii_lowercase) {
+            Some(b'p') => Ok(Group::Process),
+            Some(b'n') => Ok(Group::Name),
+            Some(b'b') => Ok(Group::Binary),
+            Some(b'f') => Ok(Group::Flatpak),
+            _          => Err(CLIError::Group(s.to_owned())),
+        }
+    }
+}
+
+/// Value that a grouped slider displays.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum GroupVolume {
+    #[default]
+    Max,
+    Mean,
+}
+
+impl TryFrom<&String> for GroupVolume {
+    type Error = CLIError;
+
+    fn try_from(s: &String) -> Result<Self, Self::Error> {
+        match s.to_ascii_lowercase().as_str() {
+            "max"  => Ok(GroupVolume::Max),
+            "mean" => Ok(GroupVolume::Mean),
+            _      => Err(CLIError::GroupVolume(s.to_owned())),
+        }
+    }
+}
+
+impl Group {
+    fn key(&self, client: &OutputClient) -> Option<String> {
+        match self {
+            Group::Process => client.process.map(|pid| pid.to_string()),
+            Group::Name    => Some(client.name.clone()).filter(|name| !name.is_empty()),
+            Group::Binary  => client.binary.clone(),
+            Group::Flatpak => client.app_id.clone(),
+        }
+    }
+}
+
+impl TryFrom<&String> for Sort {
+    type Error = CLIError;
+
+    fn try_from(s: &String) -> Result<Self, Self::Error> {
+        match s.to_ascii_lowercase().as_str() {
+            "arrival"                => Ok(Sort::Arrival),
+            "name" | "alphabetical"  => Ok(Sort::Name),
+            "activity"               => Ok(Sort::Activity),
+            "volume"                 => Ok(Sort::Volume),
+            "priority"               => Ok(Sort::Priority),
+            _                        => Err(CLIError::Sort(s.to_owned())),
+        }
+    }
+}
 
 #[derive(Debug)]
 pub enum SliderMessage {
     Mute,
     ValueChange(f64),
     Removed,
-    ServerChange(Box<OutputClient>),
-    ServerPeak(f32),
+    /// Client reported by the server, with the volume to show instead while our own writes are in flight
+    ServerChange(Box<OutputClient>, Option<VolumeLevels>),
+    ServerPeak(Peaks),
+    Stream(StreamOutput),
+    Expand,
     Refresh,
+    /// Server refused the last change, slider goes back to what the server has confirmed
+    Failed,
+    /// Slider controls another client from now on
+    Retarget(Box<OutputClient>),
+    /// Context menu was requested at a point of the slider
+    OpenMenu(f64, f64),
+    /// Slider is the only one heard, or not anymore
+    Solo(bool),
+    /// Pointer was pressed on the slider or released
+    Drag(bool),
+    Menu(MenuAction),
+    #[cfg(feature = "Mpris")]
+    Media(Option<crate::mpris::Track>),
+    #[cfg(feature = "Mpris")]
+    MediaAction(crate::mpris::Action),
 }
 
 #[derive(Debug)]
 pub enum SliderCommand {
     Peak,
     Cork,
+    Recover,
 }
 
 pub struct Sliders {
     pub container: FactoryVecDeque<Slider>,
     pub direction: GrowthDirection,
-    pub per_process: bool,
+    pub group: Option<Group>,
+    pub group_volume: GroupVolume,
+    pub sort: Sort,
+    /// Application names that always come first
+    pub pins: Vec<String>,
+    /// Application names in order for `Sort::Priority`
+    pub priority: Vec<String>,
+    /// Application names hidden until the mixer restarts
+    hidden: Vec<String>,
+    /// Sinks offered by the context menus, in order of their menu items
+    outputs: Vec<u32>,
+    arrivals: u64,
 }
 
 impl Sliders {
@@ -51,7 +179,14 @@ impl Sliders {
         Self {
             container,
             direction: GrowthDirection::BottomRight,
-            per_process: false,
+            group: None,
+            group_volume: GroupVolume::Max,
+            sort: Sort::Arrival,
+            pins: Vec::new(),
+            priority: Vec::new(),
+            hidden: Vec::new(),
+            outputs: Vec::new(),
+            arrivals: 0,
         }
     }
 
@@ -64,13 +199,29 @@ impl Sliders {
     }
 
     pub fn push_client(&mut self, client: OutputClient) {
+        let hardware = client.kind.contains(server::Kind::Hardware);
+
+        // Default sink has changed, master slider moves to the new one
+        if hardware {
+            if let Some(i) = self.container.iter().position(|slider| slider.kind.contains(server::Kind::Hardware)) {
+                self.container.send(i, SliderMessage::Retarget(Box::new(client)));
+                return
+            }
+        }
+
+        if self.hidden.iter().any(|name| name.eq_ignore_ascii_case(&client.name)) {
+            return
+        }
+
         let mut sliders = self.container.guard();
 
-        if self.per_process && client.process.is_some() {
-            let pos = sliders.iter_mut().position(|slider| slider.process == client.process);
+        let key = self.group.filter(|_| !hardware).and_then(|group| group.key(&client));
+
+        if key.is_some() {
+            let pos = sliders.iter().position(|slider| slider.group == key);
 
             if let Some(i) = pos {
-                sliders.get_mut(i).unwrap().clients.push(SmallClient::from(&client));
+                sliders.get_mut(i).unwrap().add_client(&client);
                 sliders.drop();
 
                 self.container.send(i, SliderMessage::Refresh);
@@ -79,26 +230,76 @@ impl Sliders {
             }
         }
 
-        match self.direction {
-            GrowthDirection::TopLeft => sliders.push_front(client),
-            GrowthDirection::BottomRight => sliders.push_back(client),
+        let arrival = self.arrivals;
+        self.arrivals += 1;
+
+        let slider = match self.direction {
+            GrowthDirection::TopLeft => {
+                sliders.push_front(client);
+                sliders.front_mut()
+            }
+            GrowthDirection::BottomRight => {
+                sliders.push_back(client);
+                sliders.back_mut()
+            }
         };
 
+        if let Some(slider) = slider {
+            slider.arrival = arrival;
+            slider.group = key;
+            slider.group_volume = self.group_volume;
+            slider.pinned = rank(&self.pins, &slider.name) != usize::MAX;
+        }
+
+        sliders.drop();
+
+        self.sort();
+    }
+
+    /// Moves sliders into their places, without recreating them.
+    pub fn sort(&mut self) {
+        if self.sort == Sort::Arrival && self.pins.is_empty() {
+            return
+        }
+
+        // Slider would slip away from the pointer, it's sorted once released instead
+        if self.container.iter().any(|slider| slider.dragging) {
+            return
+        }
+
+        let Self { container, direction, sort, pins, priority, .. } = self;
+
+        let mut sliders = container.guard();
+
+        let mut order: Vec<usize> = (0..sliders.len()).collect();
+        order.sort_by(|&a, &b| compare(sliders.get(a).unwrap(), sliders.get(b).unwrap(), *sort, pins, priority));
+
+        // Sliders closer to the anchor come first
+        if let GrowthDirection::TopLeft = direction {
+            order.reverse();
+        }
+
+        let mut current: Vec<usize> = (0..sliders.len()).collect();
+
+        for (target, i) in order.into_iter().enumerate() {
+            let position = current.iter().position(|&j| j == i).unwrap();
+
+            if position != target {
+                sliders.move_to(position, target);
+                let i = current.remove(position);
+                current.insert(target, i);
+            }
+        }
+
         sliders.drop();
     }
 
+    /// Removes a software client, master slider is never removed.
     pub fn remove(&mut self, id: u32) {
         let mut sliders = self.container.guard();
 
-        let i = sliders.iter_mut().position(|slider| {
-            match slider.clients.iter().position(|client| client.id == id) {
-                Some(pos) => {
-                    slider.clients.remove(pos);
-                    true
-                }
-                None => false,
-            }
-        });
+        let i = sliders.iter_mut()
+            .position(|slider| !slider.kind.contains(server::Kind::Hardware) && slider.remove_client(id));
 
         match i {
             Some(i) if sliders.get(i).unwrap().clients.is_empty() => {
@@ -115,51 +316,325 @@ impl Sliders {
 
     pub fn clear(&mut self) {
         self.container.guard().clear();
+
+        self.outputs.clear();
+        self.container.widget().outputs().remove_all();
     }
 
-    pub fn contains(&self, id: u32) -> bool {
-        self.container
-            .iter()
-            .any(|e| e.clients.iter().any(|c| c.id == id))
+    /// Offers a sink in the context menus.
+    pub fn add_output(&mut self, sink: &Sink) {
+        if self.outputs.contains(&sink.id) {
+            return
+        }
+
+        let item = gio::MenuItem::new(Some(&sink.description), None);
+        item.set_action_and_target_value(Some("slider.move"), Some(&sink.name.to_variant()));
+
+        self.container.widget().outputs().append_item(&item);
+        self.outputs.push(sink.id);
     }
 
-    fn position(&self, id: u32) -> Option<usize> {
-        self.container.iter().position(|slider| slider.clients.iter().any(|c| c.id == id))
+    pub fn remove_output(&mut self, id: u32) {
+        let Some(i) = self.outputs.iter().position(|output| *output == id) else { return };
+
+        self.container.widget().outputs().remove(i as i32);
+        self.outputs.remove(i);
     }
 
-    pub fn send(&self, id: u32, message: SliderMessage) {
-        if let Some(index) = self.position(id) {
+    /// Removes sliders of an application and keeps its new clients away.
+    pub fn hide(&mut self, name: &str) {
+        self.hidden.push(name.to_owned());
+
+        let mut sliders = self.container.guard();
+
+        loop {
+            let i = sliders.iter().position(|slider| {
+                !slider.kind.contains(server::Kind::Hardware) && slider.name.eq_ignore_ascii_case(name)
+            });
+
+            let Some(i) = i else { break };
+            sliders.remove(i);
+        }
+    }
+
+    /// Pins an application or unpins it if it already was.
+    pub fn toggle_pin(&mut self, name: &str) {
+        match self.pins.iter().position(|pin| pin.eq_ignore_ascii_case(name)) {
+            Some(i) => { self.pins.remove(i); },
+            None    => self.pins.push(name.to_owned()),
+        }
+
+        let mut sliders = self.container.guard();
+
+        for i in 0..sliders.len() {
+            let slider = sliders.get_mut(i).unwrap();
+            slider.pinned = rank(&self.pins, &slider.name) != usize::MAX;
+        }
+
+        sliders.drop();
+
+        self.sort();
+    }
+
+    /// Attaches a media player to the first slider of its application that doesn't have one yet.
+    #[cfg(feature = "Mpris")]
+    pub fn attach(&mut self, media: &crate::mpris::Media, track: Option<&crate::mpris::Track>) {
+        let mut sliders = self.container.guard();
+
+        // Player stays on a single slider, other clients of the same application don't get its controls
+        if sliders.iter().any(|slider| slider.media.bus.as_deref() == Some(&*media.bus)) {
+            return
+        }
+
+        let i = sliders.iter().position(|slider| {
+            !slider.kind.contains(server::Kind::Hardware) && slider.media.bus.is_none() && slider.media.plays(media)
+        });
+
+        let Some(i) = i else { return };
+
+        sliders.get_mut(i).unwrap().media.bus = Some(media.bus.clone());
+        sliders.drop();
+
+        self.container.send(i, SliderMessage::Media(track.cloned()));
+    }
+
+    #[cfg(feature = "Mpris")]
+    pub fn detach(&mut self, bus: &str) {
+        let mut sliders = self.container.guard();
+
+        let Some(i) = sliders.iter().position(|slider| slider.media.bus.as_deref() == Some(bus)) else { return };
+
+        sliders.get_mut(i).unwrap().media.bus = None;
+        sliders.drop();
+
+        self.container.send(i, SliderMessage::Media(None));
+    }
+
+    #[cfg(feature = "Mpris")]
+    pub fn send_track(&self, bus: &str, track: crate::mpris::Track) {
+        if let Some(i) = self.container.iter().position(|slider| slider.media.bus.as_deref() == Some(bus)) {
+            self.container.send(i, SliderMessage::Media(Some(track)));
+        }
+    }
+
+    /// Whether a software client is on a soloed slider.
+    pub fn is_soloed(&self, id: u32) -> bool {
+        self.position(id, server::Kind::Software).and_then(|i| self.container.get(i)).is_some_and(|slider| slider.soloed)
+    }
+
+    /// Software clients of every slider of an application.
+    pub fn ids_of(&self, name: &str) -> SmallVec<[u32; 3]> {
+        self.container.iter()
+            .filter(|slider| !slider.kind.contains(server::Kind::Hardware) && slider.name.eq_ignore_ascii_case(name))
+            .flat_map(Slider::ids)
+            .collect()
+    }
+
+    /// Clients of the software slider that has keyboard focus.
+    pub fn focused(&self) -> Option<SmallVec<[u32; 3]>> {
+        // Widgets are kept in the same order as sliders
+        let mut child = self.container.widget().first_child();
+        let mut i = 0;
+
+        while let Some(widget) = child {
+            if widget.focus_child().is_some() {
+                return self.container.get(i)
+                    .filter(|slider| !slider.kind.contains(server::Kind::Hardware))
+                    .map(Slider::ids)
+            }
+
+            child = widget.next_sibling();
+            i += 1;
+        }
+
+        None
+    }
+
+    // Software and hardware clients have separate ids
+    pub fn contains(&self, id: u32, kind: server::Kind) -> bool {
+        self.position(id, kind).is_some()
+    }
+
+    fn position(&self, id: u32, kind: server::Kind) -> Option<usize> {
+        let hardware = kind.contains(server::Kind::Hardware);
+
+        self.container.iter().position(|slider| {
+            slider.kind.contains(server::Kind::Hardware) == hardware && slider.clients.iter().any(|c| c.id == id)
+        })
+    }
+
+    pub fn send(&self, id: u32, kind: server::Kind, message: SliderMessage) {
+        if let Some(index) = self.position(id, kind) {
             self.container.send(index, message)
         }
     }
 }
 
+fn rank(list: &[String], name: &str) -> usize {
+    list.iter().position(|item| item.eq_ignore_ascii_case(name)).unwrap_or(usize::MAX)
+}
+
+fn compare(a: &Slider, b: &Slider, sort: Sort, pins: &[String], priority: &[String]) -> Ordering {
+    rank(pins, &a.name).cmp(&rank(pins, &b.name))
+        .then_with(|| match sort {
+            Sort::Arrival  => Ordering::Equal,
+            Sort::Name     => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            Sort::Activity => a.is_corked().cmp(&b.is_corked()),
+            Sort::Volume   => b.volume.percent().total_cmp(&a.volume.percent()),
+            Sort::Priority => rank(priority, &a.name).cmp(&rank(priority, &b.name)),
+        })
+        .then(a.arrival.cmp(&b.arrival))
+}
+
 #[tracker::track]
 pub struct Slider {
     #[do_not_track] clients: SmallVec<[SmallClient; 3]>,
-    #[do_not_track] process: Option<u32>,
+    #[do_not_track] streams: FactoryVecDeque<Stream>,
+    #[do_not_track] group: Option<String>,
+    #[do_not_track] group_volume: GroupVolume,
+    #[do_not_track] arrival: u64,
+    #[do_not_track] max_volume: f64,
+    expanded: bool,
     volume: Volume,
-    volume_percent: u8,
+    volume_percent: u32,
     muted: bool,
     corked: bool,
     name: String,
     icon: Cow<'static, str>,
-    #[no_eq] peak: f64,
+    #[no_eq] meters: Meters,
     removed: bool,
+    failed: bool,
+    soloed: bool,
     #[no_eq] updated: bool,
+    #[do_not_track] failures: u32,
     #[do_not_track] kind: server::Kind,
     #[do_not_track] corking: bool,
+    #[do_not_track] ticking: bool,
+    #[do_not_track] pinned: bool,
+    #[do_not_track] dragging: bool,
+    #[do_not_track] menu: SliderMenu,
+    #[cfg(feature = "Mpris")]
+    #[do_not_track] media: super::media::MediaControls,
 }
 
 impl Slider {
+    fn add_client(&mut self, client: &OutputClient) {
+        #[cfg(feature = "Mpris")]
+        self.media.add_client(client);
+
+        let client = SmallClient::from(client);
+
+        self.streams.guard().push_back(StreamInit { client: client.clone(), max_value: self.max_volume });
+        self.clients.push(client);
+    }
+
+    fn remove_client(&mut self, id: u32) -> bool {
+        match self.clients.iter().position(|client| client.id == id) {
+            Some(i) => {
+                self.clients.remove(i);
+                self.streams.guard().remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+
     fn is_corked(&self) -> bool {
         self.clients.iter().all(|id| id.corked)
     }
 
+    /// Volume that represents every client in the group.
+    fn group_volume(&self) -> Volume {
+        let loudest = self.clients.iter()
+            .map(|client| &client.volume)
+            .max_by(|a, b| a.percent().total_cmp(&b.percent()))
+            .expect("Slider has at least one client");
+
+        match self.group_volume {
+            GroupVolume::Max => loudest.clone(),
+            GroupVolume::Mean => {
+                let mean = self.clients.iter().map(|client| client.volume.percent()).sum::<f64>() / self.clients.len() as f64;
+
+                let mut volume = loudest.clone();
+                volume.set_percent(mean);
+                volume
+            }
+        }
+    }
+
     fn is_muted(&self) -> bool {
         self.clients.iter().all(|id| id.muted)
     }
 
+    fn ids(&self) -> SmallVec<[u32; 3]> {
+        self.clients.iter().map(|client| client.id).collect()
+    }
+
+    fn handle_menu(&mut self, action: MenuAction, sender: FactorySender<Self>) {
+        let message = match action {
+            MenuAction::Solo => ElementMessage::Solo { ids: self.ids() },
+            MenuAction::Move(output) => ElementMessage::Move { ids: self.ids(), output },
+            MenuAction::Hide => ElementMessage::Hide { name: self.name.clone() },
+            MenuAction::Pin => ElementMessage::Pin { name: self.name.clone() },
+            MenuAction::OpenMixer => ElementMessage::OpenMixer,
+            MenuAction::Copy => return self.menu.copy(&self.info()),
+            MenuAction::Reset => return self.reset_volume(sender),
+        };
+
+        let _ = sender.output(message);
+    }
+
+    /// Every client goes to 100%, keeping the balance of its channels.
+    fn reset_volume(&mut self, sender: FactorySender<Self>) {
+        let normal = curve::config().curve.position(1.0).min(self.max_volume);
+
+        for (i, client) in self.clients.iter_mut().enumerate() {
+            client.volume.set_percent(normal);
+
+            self.streams.send(i, StreamMessage::ServerChange(client.clone()));
+
+            let _ = sender.output(ElementMessage::SetVolume {
+                ids: smallvec::smallvec![client.id],
+                kind: self.kind,
+                levels: client.volume.levels.clone()
+            });
+        }
+
+        let volume = self.group_volume();
+
+        self.set_volume_percent((volume.percent() * 100.0) as u32);
+        self.set_volume(volume);
+    }
+
+    /// Plain text description of every stream, for the clipboard.
+    fn info(&self) -> String {
+        let mut info = self.name.clone();
+
+        for client in &self.clients {
+            let _ = write!(info, "\n#{} {} ({}", client.id, client.description, curve::label(&client.volume));
+
+            if client.muted {
+                info.push_str(", muted");
+            }
+
+            if let Some(process) = client.process {
+                let _ = write!(info, ", process {process}");
+            }
+
+            if let Some(binary) = &client.binary {
+                let _ = write!(info, ", binary {binary}");
+            }
+
+            if let Some(app_id) = &client.app_id {
+                let _ = write!(info, ", flatpak {app_id}");
+            }
+
+            info.push(')');
+        }
+
+        info
+    }
+
     fn description(&self) -> &str {
         if self.clients.len() == 1 {
             return self.clients[0].description.as_str()
@@ -208,6 +683,48 @@ impl FactoryComponent for Slider {
             #[track = "self.changed(Self::muted())"]
             set_class_active: ("muted", self.is_muted()),
 
+            #[track = "self.changed(Self::meters())"]
+            set_class_active: ("clipping", self.meters.is_clipping()),
+
+            #[track = "self.changed(Self::volume())"]
+            set_class_active: ("overamplified", self.volume.amplitude() > 1.0),
+
+            #[track = "self.changed(Self::expanded())"]
+            set_class_active: ("expanded", self.expanded),
+
+            #[track = "self.changed(Self::failed())"]
+            set_class_active: ("error", self.failed),
+
+            #[track = "self.changed(Self::soloed())"]
+            set_class_active: ("solo", self.soloed),
+
+            add_controller = gtk::GestureClick {
+                set_button: gtk::gdk::BUTTON_SECONDARY,
+                connect_pressed[sender] => move |_, _, x, y| {
+                    sender.input(SliderMessage::OpenMenu(x, y));
+                }
+            },
+
+            // Scales claim their drags, so presses are only observed before they get there
+            add_controller = gtk::EventControllerLegacy {
+                set_propagation_phase: gtk::PropagationPhase::Capture,
+                connect_event[sender] => move |_, event| {
+                    use gtk::gdk::EventType::*;
+
+                    // Other buttons open menus that take the release away
+                    let primary = event.downcast_ref::<gtk::gdk::ButtonEvent>()
+                        .is_none_or(|event| event.button() == gtk::gdk::BUTTON_PRIMARY);
+
+                    match event.event_type() {
+                        ButtonPress | TouchBegin if primary    => sender.input(SliderMessage::Drag(true)),
+                        ButtonRelease | TouchEnd | TouchCancel => sender.input(SliderMessage::Drag(false)),
+                        _                                      => {},
+                    }
+
+                    glib::Propagation::Proceed
+                }
+            },
+
             gtk::Image {
                 add_css_class: "icon",
                 set_use_fallback: false,
@@ -216,6 +733,7 @@ impl FactoryComponent for Slider {
                 set_visible: parent.has_icons(),
             },
 
+            #[name(details)]
             gtk::Box {
                 set_orientation: Orientation::Vertical,
 
@@ -237,23 +755,32 @@ impl FactoryComponent for Slider {
 
                 #[name(scale_wrapper)]
                 gtk::Box {
-                    #[name(scale)] // 0.00004 is a rounding error
-                    gtk::Scale::with_range(Orientation::Horizontal, 0.0, parent.max_value() + 0.00004, 0.005) {
-                        #[track = "self.changed(Self::volume())"]
-                        set_value: self.volume.percent(),
-                        set_slider_size_fixed: true,
-                        set_show_fill_level: true,
-                        set_restrict_to_fill_level: false,
-                        #[track = "self.changed(Self::peak())"]
-                        set_fill_level: self.peak,
-                        connect_value_changed[sender] => move |scale| {
-                            sender.input(SliderMessage::ValueChange(scale.value()));
+                    #[name(meter_wrapper)]
+                    gtk::Box {
+                        set_orientation: Orientation::Vertical,
+
+                        #[name(scale)] // 0.00004 is a rounding error
+                        gtk::Scale::with_range(Orientation::Horizontal, 0.0, parent.max_value() + 0.00004, curve::config().increment()) {
+                            #[track = "self.changed(Self::volume())"]
+                            set_value: self.volume.percent(),
+                            set_slider_size_fixed: true,
+                            connect_change_value: snap,
+                            connect_value_changed[sender] => move |scale| {
+                                sender.input(SliderMessage::ValueChange(scale.value()));
+                            },
+                        },
+
+                        #[name(meter)]
+                        MeterBar {
+                            set_visible: self.meters.is_enabled(),
+                            #[track = "self.changed(Self::meters())"]
+                            set_values: self.meters.values(),
                         },
                     },
 
                     gtk::Label {
-                        #[track = "self.changed(Self::volume_percent())"]
-                        set_label: &{ let mut s = self.volume_percent.to_string(); s.push('%'); s },
+                        #[track = "self.changed(Self::volume())"]
+                        set_label: &curve::label(&self.volume),
                         add_css_class: "volume",
                         set_width_chars: 5,
                         set_max_width_chars: 5,
@@ -265,6 +792,29 @@ impl FactoryComponent for Slider {
                             }
                         }
                     }
+                },
+
+                #[name(revealer)]
+                gtk::Revealer {
+                    #[track = "self.changed(Self::expanded())"]
+                    set_reveal_child: self.expanded,
+
+                    #[local_ref]
+                    streams_box -> gtk::Box {
+                        add_css_class: "streams",
+                        set_orientation: Orientation::Vertical,
+                    }
+                }
+            },
+
+            gtk::Button {
+                add_css_class: "expand",
+                set_icon_name: "pan-down-symbolic",
+                set_valign: Align::Start,
+                #[track = "self.changed(Self::updated())"]
+                set_visible: self.clients.len() > 1,
+                connect_clicked[sender] => move |_| {
+                    sender.input(SliderMessage::Expand);
                 }
             }
         }
@@ -274,10 +824,15 @@ impl FactoryComponent for Slider {
         let parent = root.parent().expect("Slider has a parent")
             .downcast::<Self::ParentWidget>().expect("Slider parent is a SliderBox");
 
+        let streams_box = &self.streams.widget().clone();
+
         let widgets = view_output!();
 
         match parent.orientation() {
             Orientation::Horizontal => {
+                streams_box.set_orientation(Orientation::Horizontal);
+                widgets.revealer.set_transition_type(gtk::RevealerTransitionType::SlideRight);
+
                 widgets.root.set_orientation(Orientation::Vertical);
                 widgets.root.set_halign(Align::Center);
 
@@ -288,26 +843,33 @@ impl FactoryComponent for Slider {
                 widgets.description.set_halign(Align::Center);
 
                 widgets.scale_wrapper.set_orientation(Orientation::Vertical);
+                widgets.meter_wrapper.set_orientation(Orientation::Horizontal);
+                widgets.meter_wrapper.set_vexpand(true);
 
                 widgets.scale.set_orientation(Orientation::Vertical);
                 widgets.scale.set_vexpand(true);
                 widgets.scale.set_inverted(true);
+
+                widgets.meter.set_vertical(true);
             }
             Orientation::Vertical => {
                 widgets.name.set_halign(Align::Start);
                 widgets.description.set_halign(Align::Start);
 
-                widgets.scale.set_hexpand(true);
+                widgets.meter_wrapper.set_hexpand(true);
             }
             _ => unreachable!("Slider recieved an unknown orientation from parent"),
         }
 
-        widgets.scale.connect_fill_level_notify({
-            let trough = widgets.scale.first_child().expect("getting GtkRange from GtkScale");
-            let fill = trough.first_child().expect("getting fill from GtkRange");
+        mark_normal(&widgets.scale, parent.max_value());
 
-            move |_| fill.queue_resize()
-        });
+        #[cfg(feature = "Mpris")]
+        self.media.build(&widgets.details, &sender);
+
+        // Master slider has nothing to offer besides its volume
+        if !self.kind.contains(server::Kind::Hardware) {
+            self.menu.build(&widgets.root, &parent.outputs(), &sender);
+        }
 
         widgets.root.add_tick_callback({
             const DELAY: Duration = Duration::from_millis(500);
@@ -327,55 +889,84 @@ impl FactoryComponent for Slider {
     }
 
     fn init_model(init: Self::Init, _: &DynamicIndex, sender: FactorySender<Self>) -> Self {
-        sender.command(|sender, shutdown| {
-            shutdown.register(async move {
-                let mut interval = tokio::time::interval(Duration::from_millis(10));
+        let volume_percent = (init.volume.percent() * 100.0) as u32;
+        let client = SmallClient::from(&init);
 
-                loop {
-                    interval.tick().await;
-                    sender.emit(SliderCommand::Peak);
-                }
-            })
-            .drop_on_shutdown()
-        });
+        #[cfg(feature = "Mpris")]
+        let mut media = super::media::MediaControls::default();
+
+        #[cfg(feature = "Mpris")]
+        media.add_client(&init);
 
-        let volume_percent = (init.volume.percent() * 100.0) as u8;
-        let clients = [
-            SmallClient::from(&init),
-            SmallClient::default(),
-            SmallClient::default()
-        ];
+        let mut streams = FactoryVecDeque::builder()
+            .launch(gtk::Box::default())
+            .forward(sender.input_sender(), SliderMessage::Stream);
+
+        streams.guard().push_back(StreamInit { client: client.clone(), max_value: init.max_volume });
 
         Self {
-            clients: SmallVec::from_buf_and_len(clients, 1),
-            process: init.process,
+            clients: smallvec::smallvec![client],
+            streams,
+            group: None,
+            group_volume: GroupVolume::Max,
+            arrival: 0,
+            max_volume: init.max_volume,
+            expanded: false,
             name: init.name,
             icon: client_icon(init.icon, volume_percent, init.muted),
             volume: init.volume,
             volume_percent,
             muted: init.muted,
             corked: init.corked,
-            peak: 0.0,
+            meters: Meters::default(),
             removed: false,
+            failed: false,
+            soloed: false,
             kind: init.kind,
             updated: false,
+            failures: 0,
 
             corking: false,
+            ticking: false,
+            pinned: false,
+            dragging: false,
+            menu: SliderMenu::default(),
+
+            #[cfg(feature = "Mpris")]
+            media,
 
             tracker: 0,
         }
     }
 
-    fn update_cmd(&mut self, cmd: Self::CommandOutput, _: FactorySender<Self>) {
+    fn shutdown(&mut self, _: &mut Self::Widgets, _: Sender<Self::Output>) {
+        self.menu.unparent();
+    }
+
+    fn update_cmd(&mut self, cmd: Self::CommandOutput, sender: FactorySender<Self>) {
         self.reset();
 
         match cmd {
-            SliderCommand::Peak => if self.peak > 0.0 {
-                self.set_peak((self.peak - 0.01).max(0.0));
+            SliderCommand::Peak => {
+                if self.meters.tick(Instant::now()) {
+                    self.update_meters(|_| {});
+                }
+
+                self.ticking = !self.meters.is_idle();
+
+                if self.ticking {
+                    sender.oneshot_command(meter_tick());
+                }
             },
             SliderCommand::Cork => if self.corking {
                 self.corking = false;
                 self.set_corked(!self.corked);
+
+                let _ = sender.output(ElementMessage::Reorder);
+            },
+            SliderCommand::Recover => {
+                self.failures -= 1;
+                self.set_failed(self.failures > 0);
             }
         }
     }
@@ -385,28 +976,72 @@ impl FactoryComponent for Slider {
 
        match message {
            SliderMessage::ServerPeak(peak) => {
-               let peak = (peak * 0.9) as f64;
+               self.meters.feed(&peak);
 
-               if peak > self.peak + 0.035 {
-                   self.set_peak(peak + 0.015);
+               if !self.ticking {
+                   self.ticking = true;
+                   sender.oneshot_command(meter_tick());
                }
            },
            SliderMessage::ValueChange(v) => {
-               if self.volume_percent != 0 {
-                   self.set_peak(100.0 * self.peak * v / self.volume_percent as f64);
-               }
+               let amplitude = self.volume.amplitude();
+               let levels = self.volume.levels.clone();
 
                self.volume.set_percent(v);
 
-               let _ = sender.output(ElementMessage::SetVolume {
-                   ids: self.clients.iter().map(|client| client.id).collect(),
-                   kind: self.kind,
-                   levels: self.volume.levels.clone()
-               });
+               if amplitude > 0.0 {
+                   let ratio = self.volume.amplitude() / amplitude;
+                   self.get_mut_meters().gain(ratio);
+               }
+
+               if self.clients.len() == 1 {
+                   self.clients[0].volume = self.volume.clone();
+
+                   let _ = sender.output(ElementMessage::SetVolume {
+                       ids: self.clients.iter().map(|client| client.id).collect(),
+                       kind: self.kind,
+                       levels: self.volume.levels.clone()
+                   });
+
+                   return
+               }
+
+               // Server echoes of the group volume put the slider where it already is
+               if *self.volume.levels == *levels {
+                   return
+               }
+
+               let curve = curve::config().curve;
+               let ratio = self.volume.amplitude() / amplitude;
+
+               // Every client in the group is scaled by the same ratio of amplitudes to keep their balance
+               // with any curve, unless there is nothing to scale from.
+               for (i, client) in self.clients.iter_mut().enumerate() {
+                   let percent = match amplitude > 0.0 {
+                       true  => curve.position(client.volume.amplitude() * ratio),
+                       false => v,
+                   };
+
+                   let levels = client.volume.levels.clone();
+                   client.volume.set_percent(percent.min(self.max_volume));
+
+                   // Only clients that actually moved are sent
+                   if *client.volume.levels == *levels {
+                       continue
+                   }
+
+                   self.streams.send(i, StreamMessage::ServerChange(client.clone()));
+
+                   let _ = sender.output(ElementMessage::SetVolume {
+                       ids: smallvec::smallvec![client.id],
+                       kind: self.kind,
+                       levels: client.volume.levels.clone()
+                   });
+               }
            },
            SliderMessage::Mute => {
                let _ = sender.output(ElementMessage::SetMute {
-                   ids: self.clients.iter().map(|client| client.id).collect(),
+                   ids: self.ids(),
                    kind: self.kind,
                    flag: !self.is_muted()
                });
@@ -414,19 +1049,39 @@ impl FactoryComponent for Slider {
            SliderMessage::Removed => {
                self.set_removed(true);
            }
-           SliderMessage::ServerChange(client) => {
-               if let Some(existing) = self.clients.iter_mut().find(|c| c.id == client.id) {
-                   let new: SmallClient = client.as_ref().into();
+           SliderMessage::Stream(StreamOutput::SetVolume { id, levels }) => {
+               let _ = sender.output(ElementMessage::SetVolume {
+                   ids: smallvec::smallvec![id],
+                   kind: self.kind,
+                   levels,
+               });
+           }
+           SliderMessage::Stream(StreamOutput::SetMute { id, flag }) => {
+               let _ = sender.output(ElementMessage::SetMute {
+                   ids: smallvec::smallvec![id],
+                   kind: self.kind,
+                   flag,
+               });
+           }
+           SliderMessage::Expand => {
+               self.set_expanded(!self.expanded);
+           }
+           SliderMessage::ServerChange(client, shown) => {
+               if let Some(i) = self.clients.iter().position(|c| c.id == client.id) {
+                   let new = SmallClient::shown(&client, shown);
 
                    // TODO: This is really wasteful, please do something about it T_T
-                   if *existing != new {
-                       *existing = new;
+                   if self.clients[i] != new {
+                       self.streams.send(i, StreamMessage::ServerChange(new.clone()));
+                       self.clients[i] = new;
                        self.set_updated(true);
                    }
                }
 
-               self.set_volume_percent((client.volume.percent() * 100.0) as u8);
-               self.set_volume(client.volume);
+               let volume = self.group_volume();
+
+               self.set_volume_percent((volume.percent() * 100.0) as u32);
+               self.set_volume(volume);
                self.set_name(client.name);
                self.set_muted(self.is_muted());
                self.set_icon(client_icon(client.icon, self.volume_percent, self.muted));
@@ -439,25 +1094,116 @@ impl FactoryComponent for Slider {
                }
 
                self.corking = self.corked != self.is_corked();
+
+               if self.changed(Slider::name()) || self.changed(Slider::volume()) {
+                   let _ = sender.output(ElementMessage::Reorder);
+               }
+           },
+           #[cfg(feature = "Mpris")]
+           SliderMessage::Media(track) => {
+               self.media.set_track(track.as_ref());
+           },
+           #[cfg(feature = "Mpris")]
+           SliderMessage::MediaAction(action) => {
+               if let Some(bus) = self.media.bus.clone() {
+                   let _ = sender.output(ElementMessage::Media { bus, action });
+               }
+           },
+           SliderMessage::OpenMenu(x, y) => {
+               self.menu.popup(x, y, self.pinned, self.soloed);
+           },
+           SliderMessage::Solo(flag) => {
+               self.set_soloed(flag);
+           },
+           SliderMessage::Drag(flag) => {
+               self.dragging = flag;
+
+               // Order has been held back while the pointer was down
+               if !flag {
+                   let _ = sender.output(ElementMessage::Reorder);
+               }
+           },
+           SliderMessage::Menu(action) => self.handle_menu(action, sender),
+           SliderMessage::Retarget(client) => {
+               self.clients.clear();
+               self.streams.guard().clear();
+
+               self.kind = client.kind;
+               self.max_volume = client.max_volume;
+               self.add_client(&client);
+
+               self.update(SliderMessage::ServerChange(client, None), sender);
+               self.set_updated(true);
            },
            SliderMessage::Refresh => {
+               let volume = self.group_volume();
+
+               self.set_volume_percent((volume.percent() * 100.0) as u32);
+               self.set_volume(volume);
                self.set_muted(self.is_muted());
                self.set_corked(self.is_corked());
                self.set_updated(true);
+
+               if self.clients.len() < 2 {
+                   self.set_expanded(false);
+               }
+
+               let _ = sender.output(ElementMessage::Reorder);
+           },
+           SliderMessage::Failed => {
+               for (i, client) in self.clients.iter_mut().enumerate() {
+                   client.rollback();
+                   self.streams.send(i, StreamMessage::ServerChange(client.clone()));
+               }
+
+               let volume = self.group_volume();
+
+               self.set_volume_percent((volume.percent() * 100.0) as u32);
+               self.set_volume(volume);
+               self.set_muted(self.is_muted());
+
+               self.failures += 1;
+               self.set_failed(true);
+
+               sender.oneshot_command(async {
+                   tokio::time::sleep(ERROR_DELAY).await;
+                   SliderCommand::Recover
+               });
            }
        }
     }
 }
 
-#[derive(Default, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct SmallClient {
-    id: u32,
-    description: String,
+    pub(super) id: u32,
+    pub(super) description: String,
+    pub(super) volume: Volume,
+    /// Last volume reported by the server
+    confirmed: Volume,
     corked: bool,
-    muted: bool,
+    pub(super) muted: bool,
+    process: Option<u32>,
+    binary: Option<String>,
+    app_id: Option<String>,
 }
 
 impl SmallClient {
+    /// Client that shows other levels than the server has confirmed.
+    fn shown(client: &OutputClient, levels: Option<VolumeLevels>) -> Self {
+        let mut new = Self::from(client);
+
+        if let Some(levels) = levels {
+            new.volume.levels = levels;
+        }
+
+        new
+    }
+
+    fn rollback(&mut self) {
+        self.volume = self.confirmed.clone();
+    }
+
     fn score(&self) -> u8 {
         (!self.corked as u8) << 3 |
         (!self.muted  as u8) << 2 |
@@ -470,13 +1216,61 @@ impl From<&OutputClient> for SmallClient {
         Self {
             id:          c.id,
             description: c.description.clone(),
+            volume:      c.volume.clone(),
+            confirmed:   c.volume.clone(),
             corked:      c.corked,
             muted:       c.muted,
+            process:     c.process,
+            binary:      c.binary.clone(),
+            app_id:      c.app_id.clone(),
         }
     }
 }
 
-fn client_icon(icon: Option<String>, volume_percent: u8, muted: bool) -> Cow<'static, str> {
+async fn meter_tick() -> SliderCommand {
+    tokio::time::sleep(METER_TICK).await;
+    SliderCommand::Peak
+}
+
+/// Snaps user changes of a scale to the configured steps, unless the fine adjustment modifier is held.
+pub(super) fn snap(scale: &gtk::Scale, _: gtk::ScrollType, value: f64) -> glib::Propagation {
+    let fine = scale.display().default_seat()
+        .and_then(|seat| seat.keyboard())
+        .is_some_and(|keyboard| keyboard.modifier_state().contains(gtk::gdk::ModifierType::CONTROL_MASK));
+
+    if fine {
+        return glib::Propagation::Proceed
+    }
+
+    let config = curve::config();
+    let current = scale.value();
+    let mut snapped = config.snap(value);
+
+    // Small scroll deltas would be rounded back to where the scale already is
+    if (snapped - current).abs() < 0.00001 && (value - current).abs() > 0.00001 {
+        snapped = config.snap(current + config.increment().copysign(value - current));
+    }
+
+    scale.set_value(snapped);
+
+    glib::Propagation::Stop
+}
+
+/// Marks 100% on scales that can go above it.
+pub(super) fn mark_normal(scale: &gtk::Scale, max_value: f64) {
+    if max_value <= 1.0 {
+        return
+    }
+
+    let position = match scale.orientation() {
+        Orientation::Horizontal => gtk::PositionType::Bottom,
+        _                       => gtk::PositionType::Right,
+    };
+
+    scale.add_mark(1.0, position, None);
+}
+
+fn client_icon(icon: Option<String>, volume_percent: u32, muted: bool) -> Cow<'static, str> {
     match icon {
         Some(name) => Cow::Owned(name),
         None => {
@@ -495,7 +1289,7 @@ fn client_icon(icon: Option<String>, volume_percent: u8, muted: bool) -> Cow<'st
 mod imp {
     use std::cell::Cell;
 
-    use gtk::glib;
+    use gtk::{gio, glib};
 
     use glib::Properties;
     use glib::subclass::types::ObjectSubclass;
@@ -518,6 +1312,9 @@ mod imp {
 
         #[property(get, set)]
         max_value: Cell<f64>,
+
+        /// Outputs that clients can be moved to, shared by the context menus
+        pub outputs: gio::Menu,
     }
 
     impl WidgetImpl for SliderBox {}
@@ -547,6 +1344,12 @@ impl SliderBox {
     pub fn default() -> Self {
         Object::builder().build()
     }
+
+    pub fn outputs(&self) -> gio::Menu {
+        use gtk::subclass::prelude::ObjectSubclassIsExt;
+
+        self.imp().outputs.clone()
+    }
 }
 
 // FactoryComponent::ParentWidget must implement FactoryView.
@@ -582,9 +1385,44 @@ impl FactoryView for SliderBox {
 
     fn factory_move_after(&self, widget: &Self::ReturnedWidget, other: &Self::ReturnedWidget) {
         self.reorder_child_after(widget, Some(other));
+        mark_moved(widget);
     }
 
     fn factory_move_start(&self, widget: &Self::ReturnedWidget) {
         self.reorder_child_after(widget, None::<&gtk::Widget>);
+        mark_moved(widget);
+    }
+}
+
+fn mark_moved(widget: &gtk::Widget) {
+    widget.add_css_class("moved");
+
+    glib::timeout_add_local_once(MOVED_DELAY, {
+        let widget = widget.downgrade();
+
+        move || if let Some(widget) = widget.upgrade() {
+            widget.remove_css_class("moved");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::mock::software_client;
+
+    #[test]
+    fn rolls_back_past_echo() {
+        let client = software_client(3);
+
+        let mut shown = client.volume.clone();
+        shown.set_percent(0.25);
+
+        // Echo of our own write shows the written volume but keeps what the server reported
+        let mut small = SmallClient::shown(&client, Some(shown.levels.clone()));
+        assert_eq!(small.volume.levels[..], shown.levels[..]);
+
+        small.rollback();
+        assert_eq!(small.volume.levels[..], client.volume.levels[..]);
     }
 }
@@ -0,0 +1,158 @@
+use relm4::{FactorySender, RelmWidgetExt};
+use relm4::prelude::{DynamicIndex, FactoryComponent};
+use relm4::factory::FactoryView;
+
+use gtk::{Justification, Orientation};
+use gtk::pango::EllipsizeMode;
+use gtk::glib::object::Cast;
+use gtk::prelude::{GestureSingleExt, OrientableExt, RangeExt, WidgetExt};
+
+use crate::curve;
+use crate::server::{Volume, VolumeLevels};
+
+use super::sliderbox::SmallClient;
+
+#[derive(Debug)]
+pub enum StreamMessage {
+    ValueChange(f64),
+    Mute,
+    ServerChange(SmallClient),
+}
+
+#[derive(Debug)]
+pub enum StreamOutput {
+    SetVolume { id: u32, levels: VolumeLevels },
+    SetMute { id: u32, flag: bool },
+}
+
+pub struct StreamInit {
+    pub client: SmallClient,
+    pub max_value: f64,
+}
+
+/// Single audio stream of a grouped slider.
+#[tracker::track]
+pub struct Stream {
+    #[do_not_track] id: u32,
+    #[do_not_track] max_value: f64,
+    description: String,
+    volume: Volume,
+    muted: bool,
+}
+
+#[relm4::factory(pub)]
+impl FactoryComponent for Stream {
+    type Init = StreamInit;
+    type Input = StreamMessage;
+    type Output = StreamOutput;
+    type ParentWidget = gtk::Box;
+    type CommandOutput = ();
+
+    view! {
+        root = gtk::Box {
+            add_css_class: "stream",
+
+            #[track = "self.changed(Self::muted())"]
+            set_class_active: ("muted", self.muted),
+
+            #[track = "self.changed(Self::volume())"]
+            set_class_active: ("overamplified", self.volume.amplitude() > 1.0),
+
+            #[name(description)]
+            gtk::Label {
+                #[track = "self.changed(Self::description())"]
+                set_label: &self.description,
+                add_css_class: "description",
+                set_ellipsize: EllipsizeMode::End,
+                set_width_chars: 8,
+            },
+
+            #[name(scale)] // 0.00004 is a rounding error
+            gtk::Scale::with_range(Orientation::Horizontal, 0.0, self.max_value + 0.00004, curve::config().increment()) {
+                #[track = "self.changed(Self::volume())"]
+                set_value: self.volume.percent(),
+                set_slider_size_fixed: true,
+                connect_change_value: super::sliderbox::snap,
+                set_hexpand: true,
+                connect_value_changed[sender] => move |scale| {
+                    sender.input(StreamMessage::ValueChange(scale.value()));
+                },
+            },
+
+            gtk::Label {
+                #[track = "self.changed(Self::volume())"]
+                set_label: &curve::label(&self.volume),
+                add_css_class: "volume",
+                set_width_chars: 5,
+                set_max_width_chars: 5,
+                set_justify: Justification::Center,
+                add_controller = gtk::GestureClick {
+                    set_button: gtk::gdk::BUTTON_PRIMARY,
+                    connect_released[sender] => move |_, _, _, _| {
+                        sender.input(StreamMessage::Mute);
+                    }
+                }
+            }
+        }
+    }
+
+    fn init_widgets(&mut self, _: &Self::Index, root: Self::Root, _: &<Self::ParentWidget as FactoryView>::ReturnedWidget, sender: FactorySender<Self>) -> Self::Widgets {
+        let parent = root.parent().expect("Stream has a parent")
+            .downcast::<Self::ParentWidget>().expect("Stream parent is a gtk::Box");
+
+        let widgets = view_output!();
+
+        // Streams follow orientation of the parent slider
+        if parent.orientation() == Orientation::Horizontal {
+            widgets.root.set_orientation(Orientation::Vertical);
+
+            widgets.scale.set_orientation(Orientation::Vertical);
+            widgets.scale.set_vexpand(true);
+            widgets.scale.set_inverted(true);
+        }
+
+        super::sliderbox::mark_normal(&widgets.scale, self.max_value);
+
+        widgets
+    }
+
+    fn init_model(init: Self::Init, _: &DynamicIndex, _: FactorySender<Self>) -> Self {
+        let client = init.client;
+
+        Self {
+            id: client.id,
+            max_value: init.max_value,
+            description: client.description,
+            volume: client.volume,
+            muted: client.muted,
+
+            tracker: 0,
+        }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: FactorySender<Self>) {
+        self.reset();
+
+        match message {
+            StreamMessage::ValueChange(v) => {
+                self.volume.set_percent(v);
+
+                let _ = sender.output(StreamOutput::SetVolume {
+                    id: self.id,
+                    levels: self.volume.levels.clone(),
+                });
+            },
+            StreamMessage::Mute => {
+                let _ = sender.output(StreamOutput::SetMute {
+                    id: self.id,
+                    flag: !self.muted,
+                });
+            },
+            StreamMessage::ServerChange(client) => {
+                self.set_description(client.description);
+                self.set_volume(client.volume);
+                self.set_muted(client.muted);
+            },
+        }
+    }
+}
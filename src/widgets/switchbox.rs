@@ -14,10 +14,13 @@ pub enum SwitchMessage {
     Activate,
     Deactivate,
     Click,
+    /// Server refused to switch to this output
+    Failed,
 }
 
 pub struct Switches {
     pub container: FactoryVecDeque<Switch>,
+    master: Option<(String, String)>,
 }
 
 impl Switches {
@@ -26,30 +29,46 @@ impl Switches {
             .launch(SwitchBox::default())
             .forward(sender, std::convert::identity);
 
-        Self { container }
+        Self { container, master: None }
     }
 
-    pub fn push(&mut self, output: Output) {
+    pub fn push(&mut self, mut output: Output) {
+        // Outputs can arrive after the master
+        output.master |= self.master.as_ref().is_some_and(|(name, port)| *name == output.name && *port == output.port);
+
         let mut switches = self.container.guard();
 
         switches.push_front(output);
         switches.drop();
     }
 
-    fn position(&self, output: Output) -> Option<usize> {
-        self.container.iter().position(|switch| switch.name == output.name && switch.port == output.port)
+    fn position(&self, name: &str, port: &str) -> Option<usize> {
+        self.container.iter().position(|switch| switch.name == name && switch.port == port)
     }
 
-    pub fn set_active(&self, output: Output) {
+    // Master is reported with every change of its volume, switches are only updated when it's another output
+    pub fn set_active(&mut self, output: Output) {
+        if self.master.as_ref().is_some_and(|(name, port)| *name == output.name && *port == output.port) {
+            return
+        }
+
         self.container.broadcast(SwitchMessage::Deactivate);
 
-        if let Some(pos) = self.position(output) {
+        if let Some(pos) = self.position(&output.name, &output.port) {
             self.container.send(pos, SwitchMessage::Activate);
         }
 
+        self.master = Some((output.name, output.port));
+    }
+
+    pub fn fail(&self, name: &str, port: &str) {
+        if let Some(pos) = self.position(name, port) {
+            self.container.send(pos, SwitchMessage::Failed);
+        }
     }
 
     pub fn clear(&mut self) {
+        self.master = None;
         self.container.guard().clear();
     }
 }
@@ -59,6 +78,8 @@ pub struct Switch {
     name:   String,
     port:   String,
     active: bool,
+    failed: bool,
+    #[do_not_track] failures: u32,
 }
 
 #[relm4::factory(pub)]
@@ -77,6 +98,9 @@ impl FactoryComponent for Switch {
             #[track = "self.changed(Self::active())"]
             set_class_active: ("master", self.active),
 
+            #[track = "self.changed(Self::failed())"]
+            set_class_active: ("error", self.failed),
+
             add_controller = gtk::GestureClick {
                 set_button: gtk::gdk::BUTTON_PRIMARY,
                 connect_pressed[sender] => move |_, _, _, _| {
@@ -106,13 +130,19 @@ impl FactoryComponent for Switch {
             name: init.name,
             port: init.port,
             active: init.master,
+            failed: false,
+            failures: 0,
 
             tracker: 0,
         }
     }
 
+    // The only command clears an error
     fn update_cmd(&mut self, _: Self::CommandOutput, _: FactorySender<Self>) {
         self.reset();
+
+        self.failures -= 1;
+        self.set_failed(self.failures > 0);
     }
 
     fn update(&mut self, message: Self::Input, sender: FactorySender<Self>) {
@@ -124,7 +154,13 @@ impl FactoryComponent for Switch {
             SwitchMessage::Click => sender.output_sender().emit(ElementMessage::SetOutput {
                 name: self.name.as_str().into(),
                 port: self.port.as_str().into(),
-            })
+            }),
+            SwitchMessage::Failed => {
+                self.failures += 1;
+                self.set_failed(true);
+
+                sender.oneshot_command(tokio::time::sleep(super::sliderbox::ERROR_DELAY));
+            }
         }
     }
 }
@@ -19,6 +19,18 @@ pub fn cache_dir() -> PathBuf {
     env_or_default("XDG_CACHE_HOME", ".cache")
 }
 
+/// Data directories in order of preference, starting with the user's one.
+pub fn data_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![env_or_default("XDG_DATA_HOME", ".local/share")];
+
+    let system = env::var_os("XDG_DATA_DIRS")
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+
+    dirs.extend(env::split_paths(&system).filter(|dir| dir.is_absolute()));
+    dirs
+}
+
 enum Platform {
     Wayland,
     X11,